@@ -40,11 +40,18 @@
       node = "${pkgs.nodejs}/bin/node";
       jq = "${pkgs.jq}/bin/jq";
       sponge = "${pkgs.moreutils}/bin/sponge";
+      aarch64-cc = pkgs.pkgsCross.aarch64-multiplatform.stdenv.cc;
+      cc = "${pkgs.stdenv.cc}/bin/cc";
+      cbindgen = "${pkgs.rust-cbindgen}/bin/cbindgen";
+      qemu-aarch64 = "${pkgs.qemu}/bin/qemu-aarch64";
       rust-toolchain = pkgs.rust-bin.selectLatestNightlyWith (
         toolchain:
         toolchain.minimal.override {
           extensions = [ "rust-src" ];
-          targets = [ "wasm32-unknown-unknown" ];
+          targets = [
+            "wasm32-unknown-unknown"
+            "aarch64-unknown-linux-gnu"
+          ];
         }
       );
     in
@@ -63,7 +70,9 @@
           --disable-reference-types \
           --disable-simd
 
-        ${jq} '.name = "pow-wasm-mvp"' pow/pkg-mvp/package.json | ${sponge} pow/pkg-mvp/package.json
+        # the protocol constants, for the page and the JS fallback which do not load the module
+        cp pow/protocol.json pow/pkg-mvp/
+        ${jq} '.name = "pow-wasm-mvp" | .files += ["protocol.json"]' pow/pkg-mvp/package.json | ${sponge} pow/pkg-mvp/package.json
       '';
 
       "wasm:build-simd".exec = ''
@@ -138,6 +147,35 @@
           "js:install"
         ];
       };
+      "test:rust-aarch64" = {
+        exec = ''
+          cd pow
+          PATH="${rust-toolchain}/bin:$PATH" \
+          CC_aarch64_unknown_linux_gnu="${aarch64-cc}/bin/${aarch64-cc.targetPrefix}cc" \
+          CARGO_TARGET_AARCH64_UNKNOWN_LINUX_GNU_LINKER="${aarch64-cc}/bin/${aarch64-cc.targetPrefix}cc" \
+          CARGO_TARGET_AARCH64_UNKNOWN_LINUX_GNU_RUNNER="${qemu-aarch64}" \
+            cargo test --target aarch64-unknown-linux-gnu --features cli
+        '';
+      };
+      "test:ffi" = {
+        exec = ''
+          cd pow
+          PATH="${rust-toolchain}/bin:$PATH" cargo build --release
+          ${cc} -Iinclude tests/ffi_smoke.c target/release/libpow.a -lm -ldl -lpthread -o target/ffi_smoke
+          target/ffi_smoke
+
+          cd ..
+          go test -tags cerberus_pow ./directives/
+        '';
+        after = [
+          "dist:build"
+        ];
+      };
+      "check:header".exec = ''
+        cd pow
+        PATH="${rust-toolchain}/bin:$PATH" ${cbindgen} --config cbindgen.toml --output include/cerberus_pow.h
+        git diff --exit-code include/cerberus_pow.h
+      '';
       "test:playwright" = {
         exec = ''
           cd web
@@ -211,6 +211,16 @@ func (m *Middleware) UnmarshalCaddyfile(d *caddyfile.Dispenser) error {
 				return d.Errf("block_only must be a boolean")
 			}
 			m.BlockOnly = blockOnly
+		case "cross_origin_isolated":
+			if !d.NextArg() {
+				m.CrossOriginIsolated = true
+				continue
+			}
+			crossOriginIsolated, ok := d.ScalarVal().(bool)
+			if !ok {
+				return d.Errf("cross_origin_isolated must be a boolean")
+			}
+			m.CrossOriginIsolated = crossOriginIsolated
 		default:
 			return d.Errf("unknown subdirective '%s'", d.Val())
 		}
@@ -134,19 +134,13 @@ func (e *Endpoint) answerHandle(w http.ResponseWriter, r *http.Request) error {
 		return respondFailure(w, r, &c.Config, "signature mismatch", false, http.StatusForbidden, ".")
 	}
 
-	saltStr, err := blake3sum(fmt.Sprintf("%s|%d|%d|%s|", challenge, nonce, ts, signature))
-	if err != nil {
-		e.logger.Error("failed to calculate salt", zap.Error(err))
-		return err
-	}
-
-	answer, err := blake3Prf(saltStr, solution)
+	answer, ok, err := verifyAnswer(fmt.Sprintf("%s|%d|%d|%s|", challenge, nonce, ts, signature), solution, c.Difficulty)
 	if err != nil {
 		e.logger.Error("failed to calculate answer", zap.Error(err))
 		return err
 	}
 
-	if !checkAnswer(response, c.Difficulty) {
+	if !ok || !checkAnswer(response, c.Difficulty) {
 		clearCookie(w, c.CookieName)
 		e.logger.Error("wrong response", zap.String("response", response), zap.Int("difficulty", c.Difficulty))
 		return respondFailure(w, r, &c.Config, "wrong response", false, http.StatusForbidden, ".")
@@ -25,6 +25,10 @@ type Middleware struct {
 	BaseURL string `json:"base_url,omitempty"`
 	// If true, the middleware will not perform any challenge. It will only block known bad IPs.
 	BlockOnly bool `json:"block_only,omitempty"`
+	// If true, the challenge page is served cross-origin isolated (COOP and COEP), so the solver workers can
+	// share memory with the page. Every asset under base_url must then be same-origin or send CORP/CORS headers,
+	// and the page loses window.opener. Off by default, in which case the workers fall back to messages.
+	CrossOriginIsolated bool `json:"cross_origin_isolated,omitempty"`
 
 	instance *core.Instance
 	logger   *zap.Logger
@@ -75,6 +79,12 @@ func (m *Middleware) invokeAuth(w http.ResponseWriter, r *http.Request) error {
 	ts := time.Now().Unix()
 	signature := calcSignature(challenge, nonce, ts, c)
 
+	if m.CrossOriginIsolated {
+		// Cross-origin isolation lets the solver workers share memory with the page, so they can be
+		// cancelled and reused instead of terminated.
+		w.Header().Set("Cross-Origin-Opener-Policy", "same-origin")
+		w.Header().Set("Cross-Origin-Embedder-Policy", "require-corp")
+	}
 	w.Header().Set(c.HeaderName, "CHALLENGE")
 	return renderTemplate(w, r, &c.Config, m.BaseURL, i18n.T(r.Context(), "challenge.title"), web.Challenge(challenge, c.Difficulty, nonce, ts, signature))
 }
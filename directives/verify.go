@@ -0,0 +1,18 @@
+//go:build !cerberus_pow
+
+package directives
+
+// verifyAnswer calculates the answer to a challenge for a nonce and checks it against the difficulty.
+//
+// Build with the cerberus_pow tag to verify with the pow crate instead, see verify_pow.go.
+func verifyAnswer(data string, nonce uint64, difficulty int) (string, bool, error) {
+	salt, err := blake3sum(data)
+	if err != nil {
+		return "", false, err
+	}
+	answer, err := blake3Prf(salt, nonce)
+	if err != nil {
+		return "", false, err
+	}
+	return answer, checkAnswer(answer, difficulty), nil
+}
@@ -0,0 +1,46 @@
+//go:build cerberus_pow
+
+package directives
+
+// The pow crate must be built first: cargo build --release --manifest-path pow/Cargo.toml
+
+/*
+#cgo CFLAGS: -I${SRCDIR}/../pow/include
+#cgo LDFLAGS: ${SRCDIR}/../pow/target/release/libpow.a -lm
+#cgo linux LDFLAGS: -ldl -lpthread
+#include "cerberus_pow.h"
+*/
+import "C"
+
+import (
+	"encoding/hex"
+	"fmt"
+	"math"
+	"unsafe"
+)
+
+// verifyAnswer calculates the answer to a challenge for a nonce and checks it against the difficulty,
+// sharing the implementation of the Cerberus message format with the solver in the browser.
+func verifyAnswer(data string, nonce uint64, difficulty int) (string, bool, error) {
+	if difficulty < 0 || uint64(difficulty) > math.MaxUint32 {
+		return "", false, fmt.Errorf("invalid difficulty %d", difficulty)
+	}
+	var hash [32]byte
+	status := C.cerberus_verify(
+		(*C.uint8_t)(unsafe.Pointer(unsafe.StringData(data))),
+		C.size_t(len(data)),
+		C.uint32_t(difficulty),
+		C.uint64_t(nonce),
+		(*C.uint8_t)(unsafe.Pointer(&hash[0])),
+	)
+	switch status {
+	case C.CERBERUS_STATUS_OK:
+		return hex.EncodeToString(hash[:]), true, nil
+	case C.CERBERUS_STATUS_INSUFFICIENT_WORK:
+		return hex.EncodeToString(hash[:]), false, nil
+	case C.CERBERUS_STATUS_INVALID_DIFFICULTY:
+		return "", false, fmt.Errorf("invalid difficulty %d", difficulty)
+	default:
+		return "", false, fmt.Errorf("cerberus_verify failed with status %d", status)
+	}
+}
@@ -0,0 +1,43 @@
+//go:build cerberus_pow
+
+package directives
+
+import (
+	"testing"
+)
+
+func TestVerifyAnswerMatchesGo(t *testing.T) {
+	for _, data := range []string{"", "challenge|0|1700000000|signature|", "Accept-Language=en,X-Real-IP=127.0.0.1"} {
+		for _, nonce := range []uint64{0, 1, 0xffffffff, 1 << 32, 0x123456789abcdef0} {
+			for _, difficulty := range []int{1, 4, 16} {
+				answer, ok, err := verifyAnswer(data, nonce, difficulty)
+				if err != nil {
+					t.Fatalf("verifyAnswer(%q, %d, %d): %v", data, nonce, difficulty, err)
+				}
+
+				salt, err := blake3sum(data)
+				if err != nil {
+					t.Fatal(err)
+				}
+				want, err := blake3Prf(salt, nonce)
+				if err != nil {
+					t.Fatal(err)
+				}
+				if answer != want {
+					t.Errorf("verifyAnswer(%q, %d, %d) = %s, want %s", data, nonce, difficulty, answer, want)
+				}
+				if ok != checkAnswer(want, difficulty) {
+					t.Errorf("verifyAnswer(%q, %d, %d) ok = %v, want %v", data, nonce, difficulty, ok, !ok)
+				}
+			}
+		}
+	}
+}
+
+func TestVerifyAnswerInvalidDifficulty(t *testing.T) {
+	for _, difficulty := range []int{-1, 0, 256} {
+		if _, _, err := verifyAnswer("challenge", 0, difficulty); err == nil {
+			t.Errorf("verifyAnswer with difficulty %d succeeded", difficulty)
+		}
+	}
+}
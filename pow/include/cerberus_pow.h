@@ -0,0 +1,191 @@
+#ifndef CERBERUS_POW_H
+#define CERBERUS_POW_H
+
+/* Generated by cbindgen from pow/src/ffi.rs. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Status codes returned by the C ABI
+ */
+typedef enum CerberusStatus {
+  /**
+   * The operation succeeded
+   */
+  CERBERUS_STATUS_OK = 0,
+  /**
+   * A pointer was null or the challenge data is not valid UTF-8
+   */
+  CERBERUS_STATUS_INVALID_ARGUMENT = 1,
+  /**
+   * The difficulty is outside the supported range
+   */
+  CERBERUS_STATUS_INVALID_DIFFICULTY = 2,
+  /**
+   * The solution does not meet the difficulty
+   */
+  CERBERUS_STATUS_INSUFFICIENT_WORK = 3,
+  /**
+   * The solver exhausted the key space without finding a solution
+   */
+  CERBERUS_STATUS_EXHAUSTED = 4,
+} CerberusStatus;
+
+/**
+ * Compute the mask for a difficulty, such that a hash can only meet the difficulty when
+ * `mask & first_word == 0`, where `first_word` is the first 4 bytes of the hash in little endian.
+ *
+ * The mask is exact for difficulties up to 16. Above that, the remaining words must be checked
+ * with `cerberus_verify`.
+ *
+ * Returns 0 if the difficulty is invalid.
+ */
+uint32_t cerberus_compute_mask(uint32_t difficulty);
+
+/**
+ * Verify a solution to a challenge.
+ *
+ * The hash of the solution is written to `out_hash` if it is not null, even if the solution
+ * does not meet the difficulty.
+ *
+ * # Safety
+ *
+ * `data` must point to `data_len` readable bytes, and `out_hash` must either be null or point to
+ * 32 writable bytes.
+ */
+enum CerberusStatus cerberus_verify(const uint8_t *data,
+                                    size_t data_len,
+                                    uint32_t difficulty,
+                                    uint64_t nonce,
+                                    uint8_t *out_hash);
+
+/**
+ * Verify a solution to a challenge hashed with a key, see `cerberus_solve_keyed`.
+ *
+ * The hash of the solution is written to `out_hash` if it is not null, even if the solution
+ * does not meet the difficulty.
+ *
+ * # Safety
+ *
+ * `key` must point to 32 readable bytes, `data` must point to `data_len` readable bytes, and
+ * `out_hash` must either be null or point to 32 writable bytes.
+ */
+enum CerberusStatus cerberus_verify_keyed(const uint8_t *key,
+                                          const uint8_t *data,
+                                          size_t data_len,
+                                          uint32_t difficulty,
+                                          uint64_t nonce,
+                                          uint8_t *out_hash);
+
+/**
+ * Verify a solution to a challenge hashed with the key derivation function.
+ *
+ * The context must be valid UTF-8, like the challenge data. The hash of the solution is written
+ * to `out_hash` if it is not null, even if the solution does not meet the difficulty.
+ *
+ * # Safety
+ *
+ * `context` must point to `context_len` readable bytes, `data` must point to `data_len` readable
+ * bytes, and `out_hash` must either be null or point to 32 writable bytes.
+ */
+enum CerberusStatus cerberus_verify_derive_key(const uint8_t *context,
+                                               size_t context_len,
+                                               const uint8_t *data,
+                                               size_t data_len,
+                                               uint32_t difficulty,
+                                               uint64_t nonce,
+                                               uint8_t *out_hash);
+
+/**
+ * Solve a challenge on the calling thread.
+ *
+ * # Safety
+ *
+ * `data` must point to `data_len` readable bytes, `out_nonce` must point to a writable `uint64_t`,
+ * and `out_hash` must either be null or point to 32 writable bytes.
+ */
+enum CerberusStatus cerberus_solve(const uint8_t *data,
+                                   size_t data_len,
+                                   uint32_t difficulty,
+                                   uint64_t *out_nonce,
+                                   uint8_t *out_hash);
+
+/**
+ * Solve a challenge hashed with a key on the calling thread.
+ *
+ * Binding challenges to a per-deployment key means solutions only verify with
+ * `cerberus_verify_keyed` and the same key.
+ *
+ * # Safety
+ *
+ * `key` must point to 32 readable bytes, `data` must point to `data_len` readable bytes,
+ * `out_nonce` must point to a writable `uint64_t`, and `out_hash` must either be null or point to
+ * 32 writable bytes.
+ */
+enum CerberusStatus cerberus_solve_keyed(const uint8_t *key,
+                                         const uint8_t *data,
+                                         size_t data_len,
+                                         uint32_t difficulty,
+                                         uint64_t *out_nonce,
+                                         uint8_t *out_hash);
+
+/**
+ * Solve a challenge on multiple threads, stopping all of them at the first solution.
+ *
+ * A thread count of 0 uses the available parallelism. The number of attempts made by all threads
+ * is written to `out_attempts` if it is not null.
+ *
+ * # Safety
+ *
+ * `data` must point to `data_len` readable bytes, `out_nonce` must point to a writable `uint64_t`,
+ * `out_hash` must either be null or point to 32 writable bytes, and `out_attempts` must either be
+ * null or point to a writable `uint64_t`.
+ */
+enum CerberusStatus cerberus_solve_parallel(const uint8_t *data,
+                                            size_t data_len,
+                                            uint32_t difficulty,
+                                            uint32_t threads,
+                                            uint64_t *out_nonce,
+                                            uint8_t *out_hash,
+                                            uint64_t *out_attempts);
+
+/**
+ * Solve a challenge with an arbitrary target on the calling thread.
+ *
+ * `target` is the 256-bit threshold in big endian: a hash meets the target when, read as a big
+ * endian integer, it is not greater than the threshold.
+ *
+ * # Safety
+ *
+ * `data` must point to `data_len` readable bytes, `target` must point to 32 readable bytes,
+ * `out_nonce` must point to a writable `uint64_t`, and `out_hash` must either be null or point to
+ * 32 writable bytes.
+ */
+enum CerberusStatus cerberus_solve_target(const uint8_t *data,
+                                          size_t data_len,
+                                          const uint8_t *target,
+                                          uint64_t *out_nonce,
+                                          uint8_t *out_hash);
+
+/**
+ * Verify a solution to a challenge with an arbitrary target.
+ *
+ * `target` is encoded as in `cerberus_solve_target`. The hash of the solution is written to
+ * `out_hash` if it is not null, even if the solution does not meet the target.
+ *
+ * # Safety
+ *
+ * `data` must point to `data_len` readable bytes, `target` must point to 32 readable bytes, and
+ * `out_hash` must either be null or point to 32 writable bytes.
+ */
+enum CerberusStatus cerberus_verify_target(const uint8_t *data,
+                                           size_t data_len,
+                                           const uint8_t *target,
+                                           uint64_t nonce,
+                                           uint8_t *out_hash);
+
+#endif  /* CERBERUS_POW_H */
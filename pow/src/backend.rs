@@ -0,0 +1,215 @@
+//! Registry of solver backends
+//!
+//! Every [`crate::solver::Solver`] implementation has a [`Backend`] with a stable name, so callers
+//! can detect, list and force the code path that runs.
+use core::fmt;
+
+/// A solver implementation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// x86_64 AVX-512, 16 lanes
+    Avx512,
+    /// x86_64 AVX2, 8 lanes
+    Avx2,
+    /// x86_64 SSE4.1, 4 lanes
+    Sse41,
+    /// aarch64 NEON, 4 lanes
+    Neon,
+    /// WebAssembly SIMD128, 8 lanes in two interleaved states
+    Simd128x2,
+    /// WebAssembly SIMD128, 4 lanes
+    Simd128,
+    /// Portable scalar code, 2 interleaved lanes
+    ScalarX2,
+    /// Portable scalar code
+    Scalar,
+}
+
+impl Backend {
+    /// All backends, most preferred first
+    pub const ALL: [Self; 8] = [
+        Self::Avx512,
+        Self::Avx2,
+        Self::Sse41,
+        Self::Neon,
+        Self::Simd128x2,
+        Self::Simd128,
+        Self::ScalarX2,
+        Self::Scalar,
+    ];
+
+    /// The name of the backend, as accepted by [`Backend::from_name`]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Avx512 => "avx512",
+            Self::Avx2 => "avx2",
+            Self::Sse41 => "sse41",
+            Self::Neon => "neon",
+            Self::Simd128x2 => "simd128x2",
+            Self::Simd128 => "simd128",
+            Self::ScalarX2 => "scalarx2",
+            Self::Scalar => "scalar",
+        }
+    }
+
+    /// Look up a backend by name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|backend| backend.name() == name)
+    }
+
+    /// Whether the backend can run here.
+    ///
+    /// On native targets this detects CPU support at runtime, on wasm32 only the backend the
+    /// bundle was built for is supported.
+    pub fn is_supported(self) -> bool {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx512 => crate::solver::avx512::CerberusSolver::is_supported(),
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => crate::solver::avx2::CerberusSolver::is_supported(),
+            #[cfg(target_arch = "x86_64")]
+            Self::Sse41 => crate::solver::sse41::CerberusSolver::is_supported(),
+            #[cfg(target_arch = "aarch64")]
+            Self::Neon => true,
+            #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+            Self::Simd128x2 => cfg!(feature = "interleave"),
+            #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+            Self::Simd128 => !cfg!(feature = "interleave"),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::ScalarX2 | Self::Scalar => true,
+            #[cfg(all(target_arch = "wasm32", not(target_feature = "simd128")))]
+            Self::ScalarX2 => cfg!(feature = "interleave-scalar"),
+            #[cfg(all(target_arch = "wasm32", not(target_feature = "simd128")))]
+            Self::Scalar => !cfg!(feature = "interleave-scalar"),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// Backends usable on this machine, most preferred first
+    pub fn supported() -> impl Iterator<Item = Self> {
+        Self::ALL
+            .into_iter()
+            .filter(|backend| backend.is_supported())
+    }
+
+    /// The most preferred backend usable on this machine
+    pub fn detect() -> Self {
+        Self::supported().next().unwrap_or(Self::Scalar)
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Evaluate `$body` with `$solver` bound to the solver type of a supported backend.
+///
+/// Evaluates to None if the backend is not compiled in.
+#[cfg(not(target_arch = "wasm32"))]
+macro_rules! with_backend {
+    ($backend:expr, $solver:ident => $body:expr) => {
+        match $backend {
+            #[cfg(target_arch = "x86_64")]
+            $crate::Backend::Avx512 => {
+                type $solver = $crate::solver::avx512::CerberusSolver;
+                Some($body)
+            }
+            #[cfg(target_arch = "x86_64")]
+            $crate::Backend::Avx2 => {
+                type $solver = $crate::solver::avx2::CerberusSolver;
+                Some($body)
+            }
+            #[cfg(target_arch = "x86_64")]
+            $crate::Backend::Sse41 => {
+                type $solver = $crate::solver::sse41::CerberusSolver;
+                Some($body)
+            }
+            #[cfg(target_arch = "aarch64")]
+            $crate::Backend::Neon => {
+                type $solver = $crate::solver::neon::CerberusSolver;
+                Some($body)
+            }
+            $crate::Backend::ScalarX2 => {
+                type $solver = $crate::solver::scalar::CerberusSolver<2>;
+                Some($body)
+            }
+            $crate::Backend::Scalar => {
+                type $solver = $crate::solver::scalar::CerberusSolver;
+                Some($body)
+            }
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    };
+}
+
+/// Evaluate `$body` with `$solver` bound to the solver type of [`Backend::detect`].
+#[cfg(not(target_arch = "wasm32"))]
+macro_rules! with_detected_backend {
+    ($solver:ident => $body:expr) => {
+        with_backend!($crate::Backend::detect(), $solver => $body)
+            .expect("the detected backend is supported")
+    };
+}
+
+/// Evaluate `$body` with `$solver` bound to [`crate::CerberusSolver`], the only backend compiled
+/// into a wasm32 build.
+#[cfg(target_arch = "wasm32")]
+macro_rules! with_detected_backend {
+    ($solver:ident => $body:expr) => {{
+        type $solver = $crate::CerberusSolver;
+        $body
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_backend_names() {
+        for backend in Backend::ALL {
+            assert_eq!(Backend::from_name(backend.name()), Some(backend));
+            assert_eq!(backend.to_string(), backend.name());
+        }
+        assert_eq!(Backend::from_name("avx3"), None);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_detect() {
+        let detected = Backend::detect();
+        assert!(detected.is_supported());
+        assert_eq!(Backend::supported().next(), Some(detected));
+        #[cfg(all(
+            target_arch = "wasm32",
+            target_feature = "simd128",
+            not(feature = "interleave")
+        ))]
+        assert_eq!(detected, Backend::Simd128);
+        #[cfg(all(
+            target_arch = "wasm32",
+            target_feature = "simd128",
+            feature = "interleave"
+        ))]
+        assert_eq!(detected, Backend::Simd128x2);
+        #[cfg(all(
+            target_arch = "wasm32",
+            not(target_feature = "simd128"),
+            not(feature = "interleave-scalar")
+        ))]
+        assert_eq!(detected, Backend::Scalar);
+        #[cfg(all(
+            target_arch = "wasm32",
+            not(target_feature = "simd128"),
+            feature = "interleave-scalar"
+        ))]
+        assert_eq!(detected, Backend::ScalarX2);
+        #[cfg(target_arch = "aarch64")]
+        assert_eq!(detected, Backend::Neon);
+    }
+}
@@ -0,0 +1,168 @@
+//! Solve a Cerberus challenge from the command line.
+//!
+//! ```text
+//! cerberus-solve [--threads N] [--backend NAME] DATA DIFFICULTY
+//! echo '{"data": "...", "difficulty": 4}' | cerberus-solve [--threads N] [--backend NAME]
+//! cerberus-solve --list-backends
+//! ```
+//!
+//! Prints the solution as `{"hash": ..., "difficulty": ..., "nonce": ...}`, the same shape the
+//! page submits.
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> std::process::ExitCode {
+    cli::main()
+}
+
+// Native threads are not available on wasm32, the page uses `process_task` instead.
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod cli {
+    use std::io::Read;
+    use std::process::ExitCode;
+
+    use pow::Backend;
+
+    const USAGE: &str = "\
+Usage: cerberus-solve [--threads N] [--backend NAME] [DATA DIFFICULTY]
+       cerberus-solve --list-backends
+
+Solves a Cerberus challenge and prints the solution as JSON.
+Without DATA and DIFFICULTY, reads {\"data\": ..., \"difficulty\": ...} from stdin.
+
+Options:
+  -t, --threads N       Number of threads, 0 for all available cores (default: 0)
+  -b, --backend NAME    Solver backend (default: the fastest supported one)
+      --list-backends   List the backends and whether this machine supports them
+  -h, --help            Print this help";
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Challenge {
+        data: String,
+        difficulty: u32,
+    }
+
+    struct Args {
+        threads: u32,
+        backend: Backend,
+        list_backends: bool,
+        challenge: Option<Challenge>,
+    }
+
+    fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+        let mut threads = 0;
+        let mut backend = Backend::detect();
+        let mut list_backends = false;
+        let mut positional = Vec::new();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-t" | "--threads" => {
+                    let value = args.next().ok_or("missing value for --threads")?;
+                    threads = value
+                        .parse()
+                        .map_err(|_| format!("invalid thread count: {value}"))?;
+                }
+                "-b" | "--backend" => {
+                    let value = args.next().ok_or("missing value for --backend")?;
+                    backend = Backend::from_name(&value)
+                        .ok_or_else(|| format!("unknown backend: {value}"))?;
+                    if !backend.is_supported() {
+                        return Err(format!(
+                            "backend {backend} is not supported on this machine"
+                        ));
+                    }
+                }
+                "--list-backends" => list_backends = true,
+                "-h" | "--help" => return Err(String::new()),
+                _ => positional.push(arg),
+            }
+        }
+
+        let challenge = match positional.as_slice() {
+            [] => None,
+            [data, difficulty] => Some(Challenge {
+                data: data.clone(),
+                difficulty: difficulty
+                    .parse()
+                    .map_err(|_| format!("invalid difficulty: {difficulty}"))?,
+            }),
+            _ => return Err("expected DATA and DIFFICULTY".to_string()),
+        };
+
+        Ok(Args {
+            threads,
+            backend,
+            list_backends,
+            challenge,
+        })
+    }
+
+    pub(crate) fn main() -> ExitCode {
+        let args = match parse_args(std::env::args().skip(1)) {
+            Ok(args) => args,
+            Err(err) => {
+                if !err.is_empty() {
+                    eprintln!("error: {err}\n");
+                }
+                eprintln!("{USAGE}");
+                return ExitCode::from(2);
+            }
+        };
+
+        if args.list_backends {
+            for backend in Backend::ALL {
+                let status = if backend == args.backend {
+                    "default"
+                } else if backend.is_supported() {
+                    "supported"
+                } else {
+                    "unsupported"
+                };
+                println!("{backend}\t{status}");
+            }
+            return ExitCode::SUCCESS;
+        }
+
+        let challenge = match args.challenge {
+            Some(challenge) => challenge,
+            None => {
+                let mut input = String::new();
+                if let Err(err) = std::io::stdin().read_to_string(&mut input) {
+                    eprintln!("error: failed to read stdin: {err}");
+                    return ExitCode::FAILURE;
+                }
+                match serde_json::from_str(&input) {
+                    Ok(challenge) => challenge,
+                    Err(err) => {
+                        eprintln!("error: invalid challenge: {err}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+        };
+
+        let Some(target) = pow::Target::from_difficulty(challenge.difficulty) else {
+            eprintln!("error: invalid difficulty {}", challenge.difficulty);
+            return ExitCode::FAILURE;
+        };
+        let Some(solution) =
+            pow::solve_parallel_backend(&challenge.data, &target, args.threads, args.backend)
+        else {
+            eprintln!(
+                "error: no solution for difficulty {} (key space exhausted)",
+                challenge.difficulty
+            );
+            return ExitCode::FAILURE;
+        };
+
+        let resp = pow::Resp {
+            hash: solution.hash.to_hex(),
+            difficulty: challenge.difficulty,
+            nonce: solution.nonce,
+        };
+        println!("{}", serde_json::to_string(&resp).unwrap());
+        ExitCode::SUCCESS
+    }
+}
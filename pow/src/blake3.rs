@@ -1,10 +1,31 @@
-//! Single chunk implementation of BLAKE3
+//! Implementation of BLAKE3
 //! https://github.com/C2SP/C2SP/blob/72cc8fe15c9290bc7814dcfd5e4f1ea5d2f66e75/BLAKE3.md
+//!
+//! The solvers only need the compression function, [`Hasher`] builds the full tree hash on top
+//! of it for everything else.
+#[macro_use]
+mod loop_macros;
+
+pub(crate) mod vector;
+pub(crate) use vector::Vector;
+
+mod trailing;
+pub(crate) use trailing::TrailingBlock;
+
 #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
 pub mod simd128;
 
-#[macro_use]
-mod loop_macros;
+#[cfg(target_arch = "aarch64")]
+pub mod neon;
+
+#[cfg(target_arch = "x86_64")]
+pub mod sse41;
+
+#[cfg(target_arch = "x86_64")]
+pub mod avx2;
+
+#[cfg(target_arch = "x86_64")]
+pub mod avx512;
 
 /// Initial hash values for BLAKE3
 pub(crate) const IV: [u32; 8] = [
@@ -12,9 +33,14 @@ pub(crate) const IV: [u32; 8] = [
 ];
 pub(crate) const FLAG_CHUNK_START: u32 = 0x01;
 pub(crate) const FLAG_CHUNK_END: u32 = 0x02;
-#[expect(unused, reason = "TODO, maybe never going to need this")]
 pub(crate) const FLAG_PARENT: u32 = 0x04;
 pub(crate) const FLAG_ROOT: u32 = 0x08;
+pub(crate) const FLAG_KEYED_HASH: u32 = 0x10;
+pub(crate) const FLAG_DERIVE_KEY_CONTEXT: u32 = 0x20;
+pub(crate) const FLAG_DERIVE_KEY_MATERIAL: u32 = 0x40;
+
+pub(crate) const BLOCK_LEN: usize = 64;
+pub(crate) const CHUNK_LEN: usize = 1024;
 
 const PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
 
@@ -163,6 +189,40 @@ pub fn compress8(
     block_len: u32,
     flags: u32,
 ) -> [u32; 8] {
+    let mut state = permute(chaining_value, block_words, counter, block_len, flags);
+    for i in 0..8 {
+        state[i] ^= state[i + 8];
+    }
+    state[..8].try_into().unwrap()
+}
+
+#[inline(always)]
+/// Full BLAKE3 compression function, including the upper half of the output that is only used
+/// for extended output.
+pub fn compress16(
+    chaining_value: &[u32; 8],
+    block_words: &[u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+) -> [u32; 16] {
+    let mut state = permute(chaining_value, block_words, counter, block_len, flags);
+    for i in 0..8 {
+        state[i] ^= state[i + 8];
+        state[i + 8] ^= chaining_value[i];
+    }
+    state
+}
+
+/// All rounds of the compression function, before the feed forward.
+#[inline(always)]
+fn permute(
+    chaining_value: &[u32; 8],
+    block_words: &[u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+) -> [u32; 16] {
     let counter_low = counter as u32;
     let counter_high = (counter >> 32) as u32;
     #[rustfmt::skip]
@@ -178,10 +238,356 @@ pub fn compress8(
         round_fixed(&mut state, &block, i);
     });
 
-    for i in 0..8 {
-        state[i] ^= state[i + 8];
+    state
+}
+
+/// Read a key as little endian words
+pub(crate) fn key_words(key: &[u8; 32]) -> [u32; 8] {
+    core::array::from_fn(|i| {
+        u32::from_le_bytes([key[i * 4], key[i * 4 + 1], key[i * 4 + 2], key[i * 4 + 3]])
+    })
+}
+
+/// Read a block of little endian words, zero padding a partial block
+fn words_from_le_bytes(bytes: &[u8]) -> [u32; 16] {
+    let mut block = [0u8; BLOCK_LEN];
+    block[..bytes.len()].copy_from_slice(bytes);
+    core::array::from_fn(|i| {
+        u32::from_le_bytes([
+            block[i * 4],
+            block[i * 4 + 1],
+            block[i * 4 + 2],
+            block[i * 4 + 3],
+        ])
+    })
+}
+
+/// The inputs to the last compression of a node, which are needed to compute either its
+/// chaining value or, for the root node, the hash.
+#[derive(Debug, Clone)]
+pub(crate) struct Output {
+    input_chaining_value: [u32; 8],
+    block_words: [u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+impl Output {
+    pub(crate) const fn new(
+        input_chaining_value: [u32; 8],
+        block_words: [u32; 16],
+        counter: u64,
+        block_len: u32,
+        flags: u32,
+    ) -> Self {
+        Self {
+            input_chaining_value,
+            block_words,
+            counter,
+            block_len,
+            flags,
+        }
+    }
+
+    fn chaining_value(&self) -> [u32; 8] {
+        compress8(
+            &self.input_chaining_value,
+            &self.block_words,
+            self.counter,
+            self.block_len,
+            self.flags,
+        )
+    }
+
+    fn root_hash(&self) -> [u32; 8] {
+        compress8(
+            &self.input_chaining_value,
+            &self.block_words,
+            0,
+            self.block_len,
+            self.flags | FLAG_ROOT,
+        )
+    }
+
+    /// A 64 byte block of extended output
+    fn root_output_block(&self, output_block_counter: u64) -> [u32; 16] {
+        compress16(
+            &self.input_chaining_value,
+            &self.block_words,
+            output_block_counter,
+            self.block_len,
+            self.flags | FLAG_ROOT,
+        )
     }
-    state[..8].try_into().unwrap()
+}
+
+/// A reader of extended output, which starts with the regular 32 byte hash and can produce up to
+/// 2^64 bytes.
+#[derive(Debug, Clone)]
+pub struct OutputReader {
+    inner: Output,
+    position: u64,
+}
+
+impl OutputReader {
+    pub(crate) const fn new(inner: Output) -> Self {
+        Self { inner, position: 0 }
+    }
+
+    /// Fill the buffer with output, continuing from where the last call left off
+    pub fn fill(&mut self, mut buf: &mut [u8]) {
+        while !buf.is_empty() {
+            let block = self
+                .inner
+                .root_output_block(self.position / BLOCK_LEN as u64);
+            let offset = (self.position % BLOCK_LEN as u64) as usize;
+            let take = (BLOCK_LEN - offset).min(buf.len());
+            for (i, out) in buf[..take].iter_mut().enumerate() {
+                let byte = offset + i;
+                *out = block[byte / 4].to_le_bytes()[byte % 4];
+            }
+            buf = &mut buf[take..];
+            self.position = self.position.wrapping_add(take as u64);
+        }
+    }
+
+    /// The position in the output stream, in bytes
+    pub const fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Seek to a position in the output stream, in bytes
+    pub fn set_position(&mut self, position: u64) {
+        self.position = position;
+    }
+}
+
+/// The state of a chunk being hashed
+#[derive(Debug, Clone)]
+pub(crate) struct ChunkState {
+    chaining_value: [u32; 8],
+    chunk_counter: u64,
+    block: [u8; BLOCK_LEN],
+    block_len: u8,
+    blocks_compressed: u8,
+    flags: u32,
+}
+
+impl ChunkState {
+    pub(crate) const fn new(key_words: [u32; 8], chunk_counter: u64, flags: u32) -> Self {
+        Self {
+            chaining_value: key_words,
+            chunk_counter,
+            block: [0; BLOCK_LEN],
+            block_len: 0,
+            blocks_compressed: 0,
+            flags,
+        }
+    }
+
+    /// The number of bytes absorbed into this chunk
+    pub(crate) const fn len(&self) -> usize {
+        BLOCK_LEN * self.blocks_compressed as usize + self.block_len as usize
+    }
+
+    const fn start_flag(&self) -> u32 {
+        if self.blocks_compressed == 0 {
+            FLAG_CHUNK_START
+        } else {
+            0
+        }
+    }
+
+    /// The chaining value after all compressed blocks, excluding the buffered block
+    pub(crate) const fn chaining_value(&self) -> [u32; 8] {
+        self.chaining_value
+    }
+
+    /// Compress the buffered block, which must be full and not be the last block of the chunk
+    pub(crate) fn compress_block(&mut self) {
+        debug_assert_eq!(self.block_len as usize, BLOCK_LEN);
+        self.chaining_value = compress8(
+            &self.chaining_value,
+            &words_from_le_bytes(&self.block),
+            self.chunk_counter,
+            BLOCK_LEN as u32,
+            self.flags | self.start_flag(),
+        );
+        self.blocks_compressed += 1;
+        self.block = [0; BLOCK_LEN];
+        self.block_len = 0;
+    }
+
+    pub(crate) fn update(&mut self, mut input: &[u8]) {
+        while !input.is_empty() {
+            // The last block of a chunk is only compressed when the chunk is finished, so a full
+            // buffer is only compressed once more input arrives
+            if self.block_len as usize == BLOCK_LEN {
+                self.compress_block();
+            }
+
+            let want = BLOCK_LEN - self.block_len as usize;
+            let take = want.min(input.len());
+            self.block[self.block_len as usize..][..take].copy_from_slice(&input[..take]);
+            self.block_len += take as u8;
+            input = &input[take..];
+        }
+    }
+
+    pub(crate) fn output(&self) -> Output {
+        Output {
+            input_chaining_value: self.chaining_value,
+            block_words: words_from_le_bytes(&self.block[..self.block_len as usize]),
+            counter: self.chunk_counter,
+            block_len: self.block_len as u32,
+            flags: self.flags | self.start_flag() | FLAG_CHUNK_END,
+        }
+    }
+}
+
+fn parent_output(
+    left_child_cv: [u32; 8],
+    right_child_cv: [u32; 8],
+    key_words: [u32; 8],
+    flags: u32,
+) -> Output {
+    let mut block_words = [0; 16];
+    block_words[..8].copy_from_slice(&left_child_cv);
+    block_words[8..].copy_from_slice(&right_child_cv);
+    Output {
+        input_chaining_value: key_words,
+        block_words,
+        counter: 0,
+        block_len: BLOCK_LEN as u32,
+        flags: FLAG_PARENT | flags,
+    }
+}
+
+/// An incremental BLAKE3 hasher
+#[derive(Debug, Clone)]
+pub struct Hasher {
+    chunk_state: ChunkState,
+    key_words: [u32; 8],
+    /// Chaining values of completed subtrees, at most one per level of the tree
+    cv_stack: [[u32; 8]; 54],
+    cv_stack_len: u8,
+    flags: u32,
+}
+
+impl Hasher {
+    const fn new_internal(key_words: [u32; 8], flags: u32) -> Self {
+        Self {
+            chunk_state: ChunkState::new(key_words, 0, flags),
+            key_words,
+            cv_stack: [[0; 8]; 54],
+            cv_stack_len: 0,
+            flags,
+        }
+    }
+
+    /// Construct a new hasher for the regular hash function
+    pub const fn new() -> Self {
+        Self::new_internal(IV, 0)
+    }
+
+    /// Construct a new hasher for the keyed hash function
+    pub fn new_keyed(key: &[u8; 32]) -> Self {
+        Self::new_internal(key_words(key), FLAG_KEYED_HASH)
+    }
+
+    /// Construct a new hasher for the key derivation function.
+    ///
+    /// The context string should be hardcoded, globally unique, and application-specific.
+    pub fn new_derive_key(context: &str) -> Self {
+        let context_key = Self::derive_key_context_key(context);
+        Self::new_internal(context_key, FLAG_DERIVE_KEY_MATERIAL)
+    }
+
+    /// Hash the context string into the key used for the key material
+    pub(crate) fn derive_key_context_key(context: &str) -> [u32; 8] {
+        Self::new_internal(IV, FLAG_DERIVE_KEY_CONTEXT)
+            .update(context.as_bytes())
+            .finalize()
+    }
+
+    fn push_stack(&mut self, cv: [u32; 8]) {
+        self.cv_stack[self.cv_stack_len as usize] = cv;
+        self.cv_stack_len += 1;
+    }
+
+    fn pop_stack(&mut self) -> [u32; 8] {
+        self.cv_stack_len -= 1;
+        self.cv_stack[self.cv_stack_len as usize]
+    }
+
+    /// Add a chunk chaining value to the tree, merging every subtree it completes.
+    ///
+    /// The number of completed subtrees is the number of trailing zero bits in the total number
+    /// of chunks so far.
+    fn add_chunk_chaining_value(&mut self, mut new_cv: [u32; 8], mut total_chunks: u64) {
+        while total_chunks & 1 == 0 {
+            new_cv = parent_output(self.pop_stack(), new_cv, self.key_words, self.flags)
+                .chaining_value();
+            total_chunks >>= 1;
+        }
+        self.push_stack(new_cv);
+    }
+
+    /// Add input to the hash state
+    pub fn update(&mut self, mut input: &[u8]) -> &mut Self {
+        while !input.is_empty() {
+            // A full chunk is only finalized once more input arrives, as it might be the root
+            if self.chunk_state.len() == CHUNK_LEN {
+                let chunk_cv = self.chunk_state.output().chaining_value();
+                let total_chunks = self.chunk_state.chunk_counter + 1;
+                self.add_chunk_chaining_value(chunk_cv, total_chunks);
+                self.chunk_state = ChunkState::new(self.key_words, total_chunks, self.flags);
+            }
+
+            let want = CHUNK_LEN - self.chunk_state.len();
+            let take = want.min(input.len());
+            self.chunk_state.update(&input[..take]);
+            input = &input[take..];
+        }
+        self
+    }
+
+    fn root_output(&self) -> Output {
+        let mut output = self.chunk_state.output();
+        let mut parent_nodes_remaining = self.cv_stack_len as usize;
+        while parent_nodes_remaining > 0 {
+            parent_nodes_remaining -= 1;
+            output = parent_output(
+                self.cv_stack[parent_nodes_remaining],
+                output.chaining_value(),
+                self.key_words,
+                self.flags,
+            );
+        }
+        output
+    }
+
+    /// Finalize the hash state and return the hash as little endian words
+    pub fn finalize(&self) -> [u32; 8] {
+        self.root_output().root_hash()
+    }
+
+    /// Finalize the hash state and return a reader of extended output
+    pub fn finalize_xof(&self) -> OutputReader {
+        OutputReader::new(self.root_output())
+    }
+}
+
+impl Default for Hasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hash the input with the regular hash function
+pub fn hash(input: &[u8]) -> [u32; 8] {
+    Hasher::new().update(input).finalize()
 }
 
 #[cfg(test)]
@@ -213,9 +619,9 @@ mod tests {
 
             let count_chunks = msg.len().div_ceil(64);
             ctr = 0;
-            let mut chunks = msg.chunks_exact(64);
+            let chunks = msg.chunks_exact(64);
             let mut output = [0u32; 8];
-            while let Some(chunk) = chunks.next() {
+            for chunk in chunks {
                 let block = core::array::from_fn(|i| {
                     u32::from_le_bytes(chunk[i * 4..i * 4 + 4].try_into().unwrap())
                 });
@@ -239,4 +645,148 @@ mod tests {
             assert_eq!(output, expected, "output mismatch (blockc: {})", blockc);
         }
     }
+
+    pub(crate) fn words_to_le_bytes(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|w| w.to_le_bytes()).collect()
+    }
+
+    pub(crate) fn test_input(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    pub(crate) const TEST_KEY: &[u8; 32] = b"whats the Elvish word for friend";
+
+    pub(crate) const TEST_CONTEXT: &str = "BLAKE3 2019-12-27 16:29:52 test vectors context";
+
+    pub(crate) const TEST_LENGTHS: [usize; 18] = [
+        0,
+        1,
+        63,
+        64,
+        65,
+        127,
+        128,
+        1023,
+        1024,
+        1025,
+        2048,
+        2049,
+        3072,
+        3073,
+        4096,
+        5 * 1024 + 7,
+        31 * 1024,
+        64 * 1024 + 1,
+    ];
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_hasher() {
+        for len in TEST_LENGTHS {
+            let input = test_input(len);
+            let expected = blake3::hash(&input);
+            assert_eq!(
+                words_to_le_bytes(&hash(&input)),
+                expected.as_bytes(),
+                "hash mismatch (len: {})",
+                len
+            );
+
+            // feeding the input in uneven pieces must not change the result
+            let mut hasher = Hasher::new();
+            for piece in input.chunks(67) {
+                hasher.update(piece);
+            }
+            assert_eq!(
+                words_to_le_bytes(&hasher.finalize()),
+                expected.as_bytes(),
+                "incremental hash mismatch (len: {})",
+                len
+            );
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_compress16() {
+        let block = words_from_le_bytes(b"IETF");
+        let flags = FLAG_CHUNK_START | FLAG_CHUNK_END | FLAG_ROOT;
+        let full = compress16(&IV, &block, 0, 4, flags);
+        assert_eq!(full[..8], compress8(&IV, &block, 0, 4, flags));
+
+        let mut expected = [0u8; 64];
+        blake3::Hasher::new()
+            .update(b"IETF")
+            .finalize_xof()
+            .fill(&mut expected);
+        assert_eq!(words_to_le_bytes(&full), expected);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_output_reader() {
+        for len in [0, 64, 1025, 5 * 1024 + 7] {
+            let input = test_input(len);
+            let mut expected = vec![0u8; 300];
+            blake3::Hasher::new()
+                .update(&input)
+                .finalize_xof()
+                .fill(&mut expected);
+
+            let mut hasher = Hasher::new();
+            hasher.update(&input);
+            let mut output = vec![0u8; 300];
+            let mut reader = hasher.finalize_xof();
+            // reads that do not line up with output blocks
+            for piece in output.chunks_mut(37) {
+                reader.fill(piece);
+            }
+            assert_eq!(output, expected, "output mismatch (len: {})", len);
+            assert_eq!(reader.position(), 300);
+            assert_eq!(output[..32], words_to_le_bytes(&hasher.finalize()));
+
+            reader.set_position(100);
+            let mut tail = [0u8; 150];
+            reader.fill(&mut tail);
+            assert_eq!(tail, expected[100..250], "seek mismatch (len: {})", len);
+        }
+
+        let mut expected = [0u8; 16];
+        let mut reader = blake3::Hasher::new().finalize_xof();
+        reader.set_position(u64::MAX - 15);
+        reader.fill(&mut expected);
+        let mut output = [0u8; 16];
+        let mut reader = Hasher::new().finalize_xof();
+        reader.set_position(u64::MAX - 15);
+        reader.fill(&mut output);
+        assert_eq!(output, expected);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_hasher_modes() {
+        for len in TEST_LENGTHS {
+            let input = test_input(len);
+
+            let expected = blake3::keyed_hash(TEST_KEY, &input);
+            let mut hasher = Hasher::new_keyed(TEST_KEY);
+            hasher.update(&input);
+            assert_eq!(
+                words_to_le_bytes(&hasher.finalize()),
+                expected.as_bytes(),
+                "keyed hash mismatch (len: {})",
+                len
+            );
+
+            let expected = blake3::derive_key(TEST_CONTEXT, &input);
+            let mut hasher = Hasher::new_derive_key(TEST_CONTEXT);
+            hasher.update(&input);
+            assert_eq!(
+                words_to_le_bytes(&hasher.finalize()),
+                expected,
+                "derived key mismatch (len: {})",
+                len
+            );
+        }
+    }
 }
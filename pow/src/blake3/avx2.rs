@@ -0,0 +1,155 @@
+//! Octa-buffer BLAKE3 routines for AVX2
+//!
+//! Everything here must be inlined into a function with `#[target_feature(enable = "avx2")]`.
+use super::vector::Vector;
+use core::arch::x86_64::*;
+
+#[inline(always)]
+unsafe fn rot16(x: __m256i) -> __m256i {
+    _mm256_shuffle_epi8(
+        x,
+        _mm256_setr_epi8(
+            2, 3, 0, 1, 6, 7, 4, 5, 10, 11, 8, 9, 14, 15, 12, 13, 2, 3, 0, 1, 6, 7, 4, 5, 10, 11,
+            8, 9, 14, 15, 12, 13,
+        ),
+    )
+}
+
+#[inline(always)]
+unsafe fn rot12(x: __m256i) -> __m256i {
+    _mm256_or_si256(_mm256_srli_epi32::<12>(x), _mm256_slli_epi32::<20>(x))
+}
+
+#[inline(always)]
+unsafe fn rot8(x: __m256i) -> __m256i {
+    _mm256_shuffle_epi8(
+        x,
+        _mm256_setr_epi8(
+            1, 2, 3, 0, 5, 6, 7, 4, 9, 10, 11, 8, 13, 14, 15, 12, 1, 2, 3, 0, 5, 6, 7, 4, 9, 10,
+            11, 8, 13, 14, 15, 12,
+        ),
+    )
+}
+
+#[inline(always)]
+unsafe fn rot7(x: __m256i) -> __m256i {
+    _mm256_or_si256(_mm256_srli_epi32::<7>(x), _mm256_slli_epi32::<25>(x))
+}
+
+impl Vector for __m256i {
+    #[inline(always)]
+    unsafe fn splat(x: u32) -> Self {
+        unsafe { _mm256_set1_epi32(x as _) }
+    }
+
+    #[inline(always)]
+    unsafe fn add(self, other: Self) -> Self {
+        unsafe { _mm256_add_epi32(self, other) }
+    }
+
+    #[inline(always)]
+    unsafe fn xor(self, other: Self) -> Self {
+        unsafe { _mm256_xor_si256(self, other) }
+    }
+
+    #[inline(always)]
+    unsafe fn ror16(self) -> Self {
+        unsafe { rot16(self) }
+    }
+
+    #[inline(always)]
+    unsafe fn ror12(self) -> Self {
+        unsafe { rot12(self) }
+    }
+
+    #[inline(always)]
+    unsafe fn ror8(self) -> Self {
+        unsafe { rot8(self) }
+    }
+
+    #[inline(always)]
+    unsafe fn ror7(self) -> Self {
+        unsafe { rot7(self) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blake3::trailing::tests::{reference_word0, single_block_state};
+    use crate::blake3::vector::{compress_mb, single_patch, Word};
+    use crate::blake3::*;
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn compress_lanes(state: [u32; 16], block: &[u32; 16]) -> [[u32; 8]; 8] {
+        let mut v = core::array::from_fn(|i| _mm256_set1_epi32(state[i] as _));
+        let nonces = _mm256_setr_epi32(0, 1, 2, 0xff, 0x1234_5678, 0x7fff_ffff, -2, -1);
+        unsafe { compress_mb::<_, Word<1>>(&mut v, block, &single_patch::<_, 1>(nonces)) };
+        core::array::from_fn(|i| unsafe { core::mem::transmute(v[i]) })
+    }
+
+    #[test]
+    fn test_compress_mb8() {
+        if !std::is_x86_feature_detected!("avx2") {
+            return;
+        }
+        let flags = FLAG_CHUNK_START | FLAG_CHUNK_END | FLAG_ROOT;
+        let mut block = [0u32; 16];
+        block[0] = u32::from_le_bytes(*b"IETF");
+        let state = setup_block(IV, 0, 8, flags);
+        let results = unsafe { compress_lanes(state, &block) };
+
+        for (lane, nonce) in [
+            0,
+            1,
+            2,
+            0xff,
+            0x1234_5678,
+            0x7fff_ffff,
+            u32::MAX - 1,
+            u32::MAX,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            block[1] = nonce;
+            let expected = compress8(&IV, &block, 0, 8, flags);
+            assert_eq!(
+                results.map(|word| word[lane]),
+                expected,
+                "lane {lane} mismatch"
+            );
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn word0_lanes(batch_id: u32, nonces: [u32; 8]) -> [u32; 8] {
+        unsafe {
+            let trailing = TrailingBlock::<__m256i>::new(&single_block_state(), batch_id);
+            core::mem::transmute(trailing.word0(_mm256_loadu_si256(nonces.as_ptr().cast())))
+        }
+    }
+
+    #[test]
+    fn test_trailing_block() {
+        if !std::is_x86_feature_detected!("avx2") {
+            return;
+        }
+        let batch_id = u32::from_le_bytes(*b"IETF");
+        let nonces = [
+            0,
+            1,
+            2,
+            0xff,
+            0x1234_5678,
+            0x7fff_ffff,
+            u32::MAX - 1,
+            u32::MAX,
+        ];
+        let results = unsafe { word0_lanes(batch_id, nonces) };
+        assert_eq!(
+            results,
+            nonces.map(|nonce| reference_word0(batch_id, nonce))
+        );
+    }
+}
@@ -19,4 +19,7 @@ macro_rules! repeat {
         { let $i = 6; $b; }
         { let $i = 7; $b; }
     };
+    ([$($n:literal),*]; $i:ident, $b:block) => {
+        $( { let $i = $n; $b; } )*
+    };
 }
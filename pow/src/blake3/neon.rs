@@ -0,0 +1,130 @@
+//! Quad-buffer BLAKE3 routines for NEON
+use super::vector::Vector;
+use core::arch::aarch64::*;
+
+#[inline(always)]
+unsafe fn rot16(x: uint32x4_t) -> uint32x4_t {
+    #[allow(unused_unsafe)]
+    unsafe {
+        vreinterpretq_u32_u16(vrev32q_u16(vreinterpretq_u16_u32(x)))
+    }
+}
+
+#[inline(always)]
+unsafe fn rot12(x: uint32x4_t) -> uint32x4_t {
+    #[allow(unused_unsafe)]
+    unsafe {
+        vsriq_n_u32::<12>(vshlq_n_u32::<20>(x), x)
+    }
+}
+
+#[inline(always)]
+unsafe fn rot8(x: uint32x4_t) -> uint32x4_t {
+    const ROT8: [u8; 16] = [1, 2, 3, 0, 5, 6, 7, 4, 9, 10, 11, 8, 13, 14, 15, 12];
+    #[allow(unused_unsafe)]
+    unsafe {
+        vreinterpretq_u32_u8(vqtbl1q_u8(vreinterpretq_u8_u32(x), vld1q_u8(ROT8.as_ptr())))
+    }
+}
+
+#[inline(always)]
+unsafe fn rot7(x: uint32x4_t) -> uint32x4_t {
+    #[allow(unused_unsafe)]
+    unsafe {
+        vsriq_n_u32::<7>(vshlq_n_u32::<25>(x), x)
+    }
+}
+
+impl Vector for uint32x4_t {
+    #[inline(always)]
+    unsafe fn splat(x: u32) -> Self {
+        unsafe { vdupq_n_u32(x) }
+    }
+
+    #[inline(always)]
+    unsafe fn add(self, other: Self) -> Self {
+        unsafe { vaddq_u32(self, other) }
+    }
+
+    #[inline(always)]
+    unsafe fn xor(self, other: Self) -> Self {
+        unsafe { veorq_u32(self, other) }
+    }
+
+    #[inline(always)]
+    unsafe fn ror16(self) -> Self {
+        unsafe { rot16(self) }
+    }
+
+    #[inline(always)]
+    unsafe fn ror12(self) -> Self {
+        unsafe { rot12(self) }
+    }
+
+    #[inline(always)]
+    unsafe fn ror8(self) -> Self {
+        unsafe { rot8(self) }
+    }
+
+    #[inline(always)]
+    unsafe fn ror7(self) -> Self {
+        unsafe { rot7(self) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blake3::trailing::tests::{reference_word0, single_block_state};
+    use crate::blake3::vector::{compress_mb, single_patch, Word};
+    use crate::blake3::*;
+
+    #[test]
+    fn test_compress_mb4() {
+        let flags = FLAG_CHUNK_START | FLAG_CHUNK_END | FLAG_ROOT;
+        let mut block = [0u32; 16];
+        block[0] = u32::from_le_bytes(*b"IETF");
+        let state = setup_block(IV, 0, 8, flags);
+        let nonces = [0, 1, 0x1234_5678, u32::MAX];
+
+        let mut v = core::array::from_fn(|i| unsafe { vdupq_n_u32(state[i]) });
+        unsafe {
+            let nonces = vld1q_u32(nonces.as_ptr());
+            compress_mb::<_, Word<1>>(&mut v, &block, &single_patch::<_, 1>(nonces));
+        }
+        let results: [[u32; 4]; 8] = core::array::from_fn(|i| {
+            let mut lanes = [0; 4];
+            unsafe { vst1q_u32(lanes.as_mut_ptr(), v[i]) };
+            lanes
+        });
+
+        for (lane, nonce) in nonces.into_iter().enumerate() {
+            block[1] = nonce;
+            let expected = compress8(&IV, &block, 0, 8, flags);
+            assert_eq!(
+                results.map(|word| word[lane]),
+                expected,
+                "lane {lane} mismatch"
+            );
+        }
+    }
+
+    #[test]
+    fn test_trailing_block() {
+        let batch_id = u32::from_le_bytes(*b"IETF");
+        let nonces = [0, 1, 0x1234_5678, u32::MAX];
+
+        let mut results = [0; 4];
+        unsafe {
+            let trailing = TrailingBlock::<uint32x4_t>::new(&single_block_state(), batch_id);
+            vst1q_u32(
+                results.as_mut_ptr(),
+                trailing.word0(vld1q_u32(nonces.as_ptr())),
+            );
+        }
+        assert_eq!(
+            results,
+            nonces.map(|nonce| reference_word0(batch_id, nonce))
+        );
+    }
+}
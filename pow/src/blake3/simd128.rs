@@ -1,11 +1,7 @@
 //! Quad-buffer BLAKE3 routines for SIMD128
-use super::*;
+use super::vector::Vector;
 use core::arch::wasm32::*;
 
-#[macro_use]
-#[path = "loop_macros.rs"]
-mod loop_macros;
-
 #[inline(always)]
 fn u32x4_ror(x: v128, shift: u32) -> v128 {
     #[allow(unused_unsafe, reason = "workaround rust-analyzer #20640")]
@@ -14,73 +10,41 @@ fn u32x4_ror(x: v128, shift: u32) -> v128 {
     }
 }
 
-#[inline(always)]
-fn g4(va: &mut v128, vb: &mut v128, vc: &mut v128, vd: &mut v128, x: v128, y: v128) {
-    #[allow(unused_unsafe)]
-    unsafe {
-        *va = u32x4_add(*va, u32x4_add(*vb, x));
-        *vd = v128_xor(*vd, *va);
-        *vd = u32x4_ror(*vd, 16);
-        *vc = u32x4_add(*vc, *vd);
-        *vb = v128_xor(*vb, *vc);
-        *vb = u32x4_ror(*vb, 12);
-        *va = u32x4_add(*va, u32x4_add(*vb, y));
-        *vd = v128_xor(*vd, *va);
-        *vd = u32x4_ror(*vd, 8);
-        *vc = u32x4_add(*vc, *vd);
-        *vb = v128_xor(*vb, *vc);
-        *vb = u32x4_ror(*vb, 7);
+#[allow(unused_unsafe, reason = "workaround rust-analyzer #20640")]
+impl Vector for v128 {
+    #[inline(always)]
+    unsafe fn splat(x: u32) -> Self {
+        unsafe { u32x4_splat(x) }
     }
-}
 
-#[inline(always)]
-pub(crate) fn compress_mb4<const PATCH_1: usize>(
-    v: &mut [v128; 16],
-    block_template: &[u32; 16],
-    patch_1: v128,
-) {
-    unsafe {
-        repeat!(7; i, {
-            macro_rules! g4 {
-                ($f:ident; $a:literal, $b:literal, $c:literal, $d:literal, $x:literal, $y:literal) => {{
-                    let [va, vb, vc, vd] = v.get_disjoint_unchecked_mut([$a, $b, $c, $d]);
-                    let ix = MESSAGE_SCHEDULE[i][$x];
-                    let iy = MESSAGE_SCHEDULE[i][$y];
-                    $f(
-                        va,
-                        vb,
-                        vc,
-                        vd,
-                        if ix == PATCH_1 {
-                            patch_1
-                        } else {
-                            u32x4_splat(block_template[ix])
-                        },
-                        if iy == PATCH_1 {
-                            patch_1
-                        } else {
-                            u32x4_splat(block_template[iy])
-                        },
-                    );
-                }};
-                ($a:literal, $b:literal, $c:literal, $d:literal, $x:literal, $y:literal) => {{
-                    g4!(g4; $a, $b, $c, $d, $x, $y);
-                }};
-            }
-            g4!(0, 4, 8, 12, 0, 1);
-            g4!(1, 5, 9, 13, 2, 3);
-            g4!(2, 6, 10, 14, 4, 5);
-            g4!(3, 7, 11, 15, 6, 7);
-
-            g4!(0, 5, 10, 15, 8, 9);
-            g4!(1, 6, 11, 12, 10, 11);
-            g4!(2, 7, 8, 13, 12, 13);
-            g4!(3, 4, 9, 14, 14, 15);
-        });
-
-        repeat!(8; i, {
-            v[i] = v128_xor(v[i], v[i + 8]);
-        });
+    #[inline(always)]
+    unsafe fn add(self, other: Self) -> Self {
+        unsafe { u32x4_add(self, other) }
+    }
+
+    #[inline(always)]
+    unsafe fn xor(self, other: Self) -> Self {
+        unsafe { v128_xor(self, other) }
+    }
+
+    #[inline(always)]
+    unsafe fn ror16(self) -> Self {
+        unsafe { u32x4_ror(self, 16) }
+    }
+
+    #[inline(always)]
+    unsafe fn ror12(self) -> Self {
+        unsafe { u32x4_ror(self, 12) }
+    }
+
+    #[inline(always)]
+    unsafe fn ror8(self) -> Self {
+        unsafe { u32x4_ror(self, 8) }
+    }
+
+    #[inline(always)]
+    unsafe fn ror7(self) -> Self {
+        unsafe { u32x4_ror(self, 7) }
     }
 }
 
@@ -90,6 +54,9 @@ mod tests {
     use blake3::Hasher;
 
     use super::*;
+    use crate::blake3::trailing::tests::{reference_word0, single_block_state};
+    use crate::blake3::vector::{compress_mb, single_patch, Word};
+    use crate::blake3::*;
 
     // The mixing function, G, which mixes either a column or a diagonal.
     fn gref(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, mx: u32, my: u32) {
@@ -118,14 +85,22 @@ mod tests {
             crate::blake3::IV[1],
         );
         let [va, vb, vc, vd] = state_v.get_disjoint_mut([0, 4, 8, 12]).unwrap();
-        g4(
-            va,
-            vb,
-            vc,
-            vd,
-            unsafe { u32x4_splat(crate::blake3::IV[0] as _) },
-            unsafe { u32x4_splat(crate::blake3::IV[1] as _) },
-        );
+        unsafe {
+            super::super::vector::g_first(
+                va,
+                vb,
+                vc,
+                vd,
+                Some(u32x4_splat(crate::blake3::IV[0] as _)),
+            );
+            super::super::vector::g_second(
+                va,
+                vb,
+                vc,
+                vd,
+                Some(u32x4_splat(crate::blake3::IV[1] as _)),
+            );
+        };
 
         for i in 0..16 {
             assert_eq!(
@@ -139,6 +114,51 @@ mod tests {
         }
     }
 
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_trailing_block() {
+        let mut v = [0u32; 16];
+        v[..8].copy_from_slice(&crate::blake3::IV);
+        v[8..12].copy_from_slice(&crate::blake3::IV[..4]);
+        v[12] = 0;
+        v[13] = 0;
+        v[14] = 4;
+        v[15] = 0x0b;
+        let trailing = unsafe { TrailingBlock::<v128>::new(&v, u32::from_le_bytes(*b"IETF")) };
+        let result = u32x4_extract_lane::<0>(unsafe { trailing.word0(u32x4_splat(0)) });
+        assert_eq!(
+            result, 0x1edea283,
+            "expected: 0x1edea283, result: {result:08x}"
+        );
+        let mut hasher = Hasher::new();
+        hasher.update(b"IETF");
+        let hash = hasher.finalize();
+        let expected = u32::from_le_bytes(hash.as_bytes()[..4].try_into().unwrap());
+        assert_eq!(result, expected);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_trailing_block_interleaved() {
+        let batch_id = u32::from_le_bytes(*b"IETF");
+        let nonces = [[0, 1, 2, 3], [0x1234_5678, 0xffff, u32::MAX - 1, u32::MAX]];
+
+        let trailing = unsafe { TrailingBlock::<[v128; 2]>::new(&single_block_state(), batch_id) };
+        let results = unsafe { trailing.word0(nonces.map(|[a, b, c, d]| u32x4(a, b, c, d))) };
+
+        for (way, lanes) in nonces.into_iter().enumerate() {
+            let mut extract = [0u32; 4];
+            unsafe { v128_store(extract.as_mut_ptr().cast(), results[way]) };
+            for (lane, nonce) in lanes.into_iter().enumerate() {
+                assert_eq!(
+                    extract[lane],
+                    reference_word0(batch_id, nonce),
+                    "way {way} lane {lane} mismatch"
+                );
+            }
+        }
+    }
+
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
     #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn test_compress_mb4() {
@@ -149,17 +169,19 @@ mod tests {
         v[13] = 0;
         v[14] = 4;
         v[15] = 0x0b;
-        let mut v = core::array::from_fn(|i| u32x4_splat(v[i] as _));
+        let mut v = core::array::from_fn(|i| [u32x4_splat(v[i] as _)]);
         let mut block = [0u32; 16];
         block[0] = u32::from_le_bytes(*b"IETF");
-        compress_mb4::<4>(&mut v, &block, u32x4_splat(0));
+        unsafe {
+            compress_mb::<_, Word<4>>(&mut v, &block, &single_patch::<_, 4>([u32x4_splat(0)]))
+        };
         let expected = [
             0x1edea283, 0xabe6f4e6, 0x24896868, 0xcfc04e8f, 0x9470c54c, 0xff82a646, 0xd6b4cbd1,
             0xe2815116,
         ];
         let mut results = [0u32; 8];
         for i in 0..8 {
-            results[i] = u32x4_extract_lane::<0>(v[i]) as u32;
+            results[i] = u32x4_extract_lane::<0>(v[i][0]) as u32;
         }
         assert_eq!(
             results, expected,
@@ -176,4 +198,31 @@ mod tests {
         }
         assert_eq!(results, expected);
     }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_compress_mb4_interleaved() {
+        let flags = FLAG_CHUNK_START | FLAG_CHUNK_END | FLAG_ROOT;
+        let mut block = [0u32; 16];
+        block[0] = u32::from_le_bytes(*b"IETF");
+        let state = setup_block(IV, 0, 8, flags);
+        let nonces = [[0, 1, 2, 3], [0x1234_5678, 0xffff, u32::MAX - 1, u32::MAX]];
+
+        let mut v = core::array::from_fn(|i| [u32x4_splat(state[i]); 2]);
+        let patch = nonces.map(|[a, b, c, d]| u32x4(a, b, c, d));
+        unsafe { compress_mb::<_, Word<1>>(&mut v, &block, &single_patch::<_, 1>(patch)) };
+
+        for (way, lanes) in nonces.into_iter().enumerate() {
+            for (lane, nonce) in lanes.into_iter().enumerate() {
+                let mut extract = [0u32; 4];
+                block[1] = nonce;
+                let expected = compress8(&IV, &block, 0, 8, flags);
+                let results: [u32; 8] = core::array::from_fn(|i| {
+                    unsafe { v128_store(extract.as_mut_ptr().cast(), v[i][way]) };
+                    extract[lane]
+                });
+                assert_eq!(results, expected, "way {way} lane {lane} mismatch");
+            }
+        }
+    }
 }
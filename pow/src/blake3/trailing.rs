@@ -0,0 +1,167 @@
+//! Compression of the trailing block of a Cerberus message
+//!
+//! In the trailing block only message word 0 (the batch ID) and word 1 (the nonce) are non-zero,
+//! and the batch ID is fixed per solver. Zero words are folded away, the part of the first round
+//! that does not depend on the nonce is computed once, and only output word 0, which the
+//! prefilter looks at, is computed.
+use super::vector::{g_first, g_second, Vector};
+use super::MESSAGE_SCHEDULE;
+
+/// A trailing block with everything that does not depend on the nonce precomputed
+#[derive(Clone, Copy)]
+pub(crate) struct TrailingBlock<V> {
+    /// The state after the nonce-independent part of the first round
+    state: [V; 16],
+    /// Message word 0
+    batch_id: V,
+}
+
+impl<V: Vector> TrailingBlock<V> {
+    /// Precompute the trailing block from its initial state, see [`super::setup_block`].
+    ///
+    /// # Safety
+    ///
+    /// The target feature of `V` must be enabled.
+    #[inline(always)]
+    pub(crate) unsafe fn new(state: &[u32; 16], batch_id: u32) -> Self {
+        unsafe {
+            let mut state = state.map(|word| V::splat(word));
+            let batch_id = V::splat(batch_id);
+
+            // the first column only needs the nonce in its second half
+            let [va, vb, vc, vd] = state.get_disjoint_unchecked_mut([0, 4, 8, 12]);
+            g_first(va, vb, vc, vd, Some(batch_id));
+
+            // the other columns only mix in zero words
+            macro_rules! column {
+                ($a:literal, $b:literal, $c:literal, $d:literal) => {{
+                    let [va, vb, vc, vd] = state.get_disjoint_unchecked_mut([$a, $b, $c, $d]);
+                    g_first(va, vb, vc, vd, None);
+                    g_second(va, vb, vc, vd, None);
+                }};
+            }
+            column!(1, 5, 9, 13);
+            column!(2, 6, 10, 14);
+            column!(3, 7, 11, 15);
+
+            Self { state, batch_id }
+        }
+    }
+
+    /// Output word 0 of the compression for the nonce in each lane.
+    ///
+    /// # Safety
+    ///
+    /// The target feature of `V` must be enabled.
+    #[inline(always)]
+    pub(crate) unsafe fn word0(&self, nonce: V) -> V {
+        unsafe {
+            let mut v = self.state;
+            let word = |ix: usize| match ix {
+                0 => Some(self.batch_id),
+                1 => Some(nonce),
+                _ => None,
+            };
+
+            macro_rules! mix {
+                ($i:ident; $a:literal, $b:literal, $c:literal, $d:literal, $x:literal, $y:literal) => {{
+                    let [va, vb, vc, vd] = v.get_disjoint_unchecked_mut([$a, $b, $c, $d]);
+                    g_first(va, vb, vc, vd, word(MESSAGE_SCHEDULE[$i][$x]));
+                    g_second(va, vb, vc, vd, word(MESSAGE_SCHEDULE[$i][$y]));
+                }};
+            }
+
+            // finish the first round
+            let [va, vb, vc, vd] = v.get_disjoint_unchecked_mut([0, 4, 8, 12]);
+            g_second(va, vb, vc, vd, Some(nonce));
+            repeat!([0]; i, {
+                mix!(i; 0, 5, 10, 15, 8, 9);
+                mix!(i; 1, 6, 11, 12, 10, 11);
+                mix!(i; 2, 7, 8, 13, 12, 13);
+                mix!(i; 3, 4, 9, 14, 14, 15);
+            });
+
+            repeat!([1, 2, 3, 4, 5]; i, {
+                mix!(i; 0, 4, 8, 12, 0, 1);
+                mix!(i; 1, 5, 9, 13, 2, 3);
+                mix!(i; 2, 6, 10, 14, 4, 5);
+                mix!(i; 3, 7, 11, 15, 6, 7);
+
+                mix!(i; 0, 5, 10, 15, 8, 9);
+                mix!(i; 1, 6, 11, 12, 10, 11);
+                mix!(i; 2, 7, 8, 13, 12, 13);
+                mix!(i; 3, 4, 9, 14, 14, 15);
+            });
+
+            // in the last round only the diagonals producing words 0 and 8 matter
+            repeat!([6]; i, {
+                mix!(i; 0, 4, 8, 12, 0, 1);
+                mix!(i; 1, 5, 9, 13, 2, 3);
+                mix!(i; 2, 6, 10, 14, 4, 5);
+                mix!(i; 3, 7, 11, 15, 6, 7);
+
+                mix!(i; 0, 5, 10, 15, 8, 9);
+                mix!(i; 2, 7, 8, 13, 12, 13);
+            });
+
+            v[0].xor(v[8])
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::blake3::*;
+
+    /// The initial state of a trailing block that is also the only block of the message
+    pub(crate) fn single_block_state() -> [u32; 16] {
+        setup_block(IV, 0, 8, FLAG_CHUNK_START | FLAG_CHUNK_END | FLAG_ROOT)
+    }
+
+    /// Word 0 of the reference BLAKE3 hash of the 8-byte message `batch_id || nonce`
+    pub(crate) fn reference_word0(batch_id: u32, nonce: u32) -> u32 {
+        let mut input = [0; 8];
+        input[..4].copy_from_slice(&batch_id.to_le_bytes());
+        input[4..].copy_from_slice(&nonce.to_le_bytes());
+        let hash = ::blake3::hash(&input);
+        u32::from_le_bytes(hash.as_bytes()[..4].try_into().unwrap())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_trailing_block_known_answer() {
+        for batch_id in [0, 1, u32::from_le_bytes(*b"IETF")] {
+            let trailing = unsafe { TrailingBlock::<u32>::new(&single_block_state(), batch_id) };
+            for nonce in [0, 1, 0x1234_5678, u32::MAX] {
+                assert_eq!(
+                    unsafe { trailing.word0(nonce) },
+                    reference_word0(batch_id, nonce),
+                    "batch {batch_id:08x} nonce {nonce:08x} mismatch"
+                );
+            }
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_trailing_block() {
+        let flags = FLAG_CHUNK_END | FLAG_ROOT;
+        let midstate = core::array::from_fn(|i| IV[i].rotate_left(i as u32) ^ 0x5555_aaaa);
+        for batch_id in [0, 1, 0xdead_beef] {
+            let trailing =
+                unsafe { TrailingBlock::<u32>::new(&setup_block(midstate, 0, 8, flags), batch_id) };
+            let interleaved = unsafe {
+                TrailingBlock::<[u32; 2]>::new(&setup_block(midstate, 0, 8, flags), batch_id)
+            };
+            for nonce in [0, 1, 2, 0x1234_5678, u32::MAX] {
+                let mut block = [0; 16];
+                block[0] = batch_id;
+                block[1] = nonce;
+                let expected = compress8(&midstate, &block, 0, 8, flags)[0];
+                assert_eq!(unsafe { trailing.word0(nonce) }, expected);
+                assert_eq!(unsafe { interleaved.word0([nonce, !nonce]) }[0], expected);
+            }
+        }
+    }
+}
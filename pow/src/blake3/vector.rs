@@ -0,0 +1,221 @@
+//! Lane-generic multi-buffer BLAKE3 compression
+//!
+//! Each SIMD backend implements [`Vector`] for its register type and gets [`compress_mb`] and
+//! [`super::TrailingBlock`] for free, hashing one message per lane.
+use super::*;
+
+/// A SIMD register of 32-bit lanes.
+///
+/// # Safety
+///
+/// The methods may only be called when the target feature of the implementation is enabled.
+pub(crate) trait Vector: Copy {
+    unsafe fn splat(x: u32) -> Self;
+    unsafe fn add(self, other: Self) -> Self;
+    unsafe fn xor(self, other: Self) -> Self;
+    unsafe fn ror16(self) -> Self;
+    unsafe fn ror12(self) -> Self;
+    unsafe fn ror8(self) -> Self;
+    unsafe fn ror7(self) -> Self;
+}
+
+/// A set of message word indices that differ between lanes.
+///
+/// All other message words are taken from the block template and broadcast to every lane.
+pub(crate) trait PatchSet {
+    /// Bit `i` is set if message word `i` is patched
+    const WORDS: u16;
+}
+
+/// Patch a single message word
+pub(crate) struct Word<const I: usize>;
+
+impl<const I: usize> PatchSet for Word<I> {
+    const WORDS: u16 = 1 << I;
+}
+
+impl<A: PatchSet, B: PatchSet> PatchSet for (A, B) {
+    const WORDS: u16 = A::WORDS | B::WORDS;
+}
+
+// The mixing function, G, on every lane at once.
+#[inline(always)]
+pub(crate) unsafe fn g<V: Vector>(va: &mut V, vb: &mut V, vc: &mut V, vd: &mut V, x: V, y: V) {
+    unsafe {
+        g_first(va, vb, vc, vd, Some(x));
+        g_second(va, vb, vc, vd, Some(y));
+    }
+}
+
+// The first half of G, mixing in message word x. None stands for a word known to be zero.
+#[inline(always)]
+pub(crate) unsafe fn g_first<V: Vector>(
+    va: &mut V,
+    vb: &mut V,
+    vc: &mut V,
+    vd: &mut V,
+    x: Option<V>,
+) {
+    unsafe {
+        *va = va.add(*vb);
+        if let Some(x) = x {
+            *va = va.add(x);
+        }
+        *vd = vd.xor(*va).ror16();
+        *vc = vc.add(*vd);
+        *vb = vb.xor(*vc).ror12();
+    }
+}
+
+// The second half of G, mixing in message word y. None stands for a word known to be zero.
+#[inline(always)]
+pub(crate) unsafe fn g_second<V: Vector>(
+    va: &mut V,
+    vb: &mut V,
+    vc: &mut V,
+    vd: &mut V,
+    y: Option<V>,
+) {
+    unsafe {
+        *va = va.add(*vb);
+        if let Some(y) = y {
+            *va = va.add(y);
+        }
+        *vd = vd.xor(*va).ror8();
+        *vc = vc.add(*vd);
+        *vb = vb.xor(*vc).ror7();
+    }
+}
+
+/// Compress one block per lane, leaving the output chaining values in `v[..8]`.
+///
+/// Message words in `P` are taken from `patches`, the rest from `block_template`.
+#[inline(always)]
+pub(crate) unsafe fn compress_mb<V: Vector, P: PatchSet>(
+    v: &mut [V; 16],
+    block_template: &[u32; 16],
+    patches: &[V; 16],
+) {
+    unsafe {
+        let word = |ix: usize| {
+            if P::WORDS & (1 << ix) != 0 {
+                patches[ix]
+            } else {
+                V::splat(block_template[ix])
+            }
+        };
+
+        repeat!(7; i, {
+            macro_rules! g {
+                ($a:literal, $b:literal, $c:literal, $d:literal, $x:literal, $y:literal) => {{
+                    let [va, vb, vc, vd] = v.get_disjoint_unchecked_mut([$a, $b, $c, $d]);
+                    g(
+                        va,
+                        vb,
+                        vc,
+                        vd,
+                        word(MESSAGE_SCHEDULE[i][$x]),
+                        word(MESSAGE_SCHEDULE[i][$y]),
+                    );
+                }};
+            }
+            g!(0, 4, 8, 12, 0, 1);
+            g!(1, 5, 9, 13, 2, 3);
+            g!(2, 6, 10, 14, 4, 5);
+            g!(3, 7, 11, 15, 6, 7);
+
+            g!(0, 5, 10, 15, 8, 9);
+            g!(1, 6, 11, 12, 10, 11);
+            g!(2, 7, 8, 13, 12, 13);
+            g!(3, 4, 9, 14, 14, 15);
+        });
+
+        repeat!(8; i, {
+            v[i] = v[i].xor(v[i + 8]);
+        });
+    }
+}
+
+/// Splat a single patched message word into a patch array for [`compress_mb`]
+#[inline(always)]
+pub(crate) unsafe fn single_patch<V: Vector, const I: usize>(patch: V) -> [V; 16] {
+    let mut patches = [unsafe { V::splat(0) }; 16];
+    patches[I] = patch;
+    patches
+}
+
+// A single lane, so the scalar solver shares the multi-buffer code path.
+impl Vector for u32 {
+    #[inline(always)]
+    unsafe fn splat(x: u32) -> Self {
+        x
+    }
+
+    #[inline(always)]
+    unsafe fn add(self, other: Self) -> Self {
+        self.wrapping_add(other)
+    }
+
+    #[inline(always)]
+    unsafe fn xor(self, other: Self) -> Self {
+        self ^ other
+    }
+
+    #[inline(always)]
+    unsafe fn ror16(self) -> Self {
+        self.rotate_right(16)
+    }
+
+    #[inline(always)]
+    unsafe fn ror12(self) -> Self {
+        self.rotate_right(12)
+    }
+
+    #[inline(always)]
+    unsafe fn ror8(self) -> Self {
+        self.rotate_right(8)
+    }
+
+    #[inline(always)]
+    unsafe fn ror7(self) -> Self {
+        self.rotate_right(7)
+    }
+}
+
+// Independent registers processed side by side, so the dependency chains of G overlap.
+impl<V: Vector, const N: usize> Vector for [V; N] {
+    #[inline(always)]
+    unsafe fn splat(x: u32) -> Self {
+        [unsafe { V::splat(x) }; N]
+    }
+
+    #[inline(always)]
+    unsafe fn add(self, other: Self) -> Self {
+        core::array::from_fn(|i| unsafe { self[i].add(other[i]) })
+    }
+
+    #[inline(always)]
+    unsafe fn xor(self, other: Self) -> Self {
+        core::array::from_fn(|i| unsafe { self[i].xor(other[i]) })
+    }
+
+    #[inline(always)]
+    unsafe fn ror16(self) -> Self {
+        self.map(|x| unsafe { x.ror16() })
+    }
+
+    #[inline(always)]
+    unsafe fn ror12(self) -> Self {
+        self.map(|x| unsafe { x.ror12() })
+    }
+
+    #[inline(always)]
+    unsafe fn ror8(self) -> Self {
+        self.map(|x| unsafe { x.ror8() })
+    }
+
+    #[inline(always)]
+    unsafe fn ror7(self) -> Self {
+        self.map(|x| unsafe { x.ror7() })
+    }
+}
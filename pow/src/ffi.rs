@@ -0,0 +1,608 @@
+//! C ABI for verifying and solving Cerberus challenges outside the browser.
+//!
+//! The header is generated with `cbindgen --config cbindgen.toml --output include/cerberus_pow.h`.
+//! `tests/ffi_smoke.c` is built against it and the staticlib, and the server verifies through it
+//! when built with the `cerberus_pow` tag, see `directives/verify_pow.go`.
+//!
+//! Challenge data is passed as a pointer and a length and must be valid UTF-8. Hashes are written
+//! as the 32 raw bytes of the BLAKE3 output.
+use crate::{Target, VerifyError};
+
+/// Status codes returned by the C ABI
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CerberusStatus {
+    /// The operation succeeded
+    Ok = 0,
+    /// A pointer was null or the challenge data is not valid UTF-8
+    InvalidArgument = 1,
+    /// The difficulty is outside the supported range
+    InvalidDifficulty = 2,
+    /// The solution does not meet the difficulty
+    InsufficientWork = 3,
+    /// The solver exhausted the key space without finding a solution
+    Exhausted = 4,
+}
+
+/// Borrow challenge data from C.
+///
+/// # Safety
+///
+/// `data` must either be null or point to `data_len` readable bytes.
+unsafe fn challenge_data<'a>(data: *const u8, data_len: usize) -> Option<&'a str> {
+    if data.is_null() {
+        return (data_len == 0).then_some("");
+    }
+    core::str::from_utf8(unsafe { core::slice::from_raw_parts(data, data_len) }).ok()
+}
+
+/// Write a hash to C, ignoring null output pointers.
+///
+/// # Safety
+///
+/// `out` must either be null or point to 32 writable bytes.
+unsafe fn write_hash(out: *mut u8, hash: &crate::Hash) {
+    if !out.is_null() {
+        unsafe { core::ptr::copy_nonoverlapping(hash.to_bytes().as_ptr(), out, 32) };
+    }
+}
+
+/// Compute the mask for a difficulty, such that a hash can only meet the difficulty when
+/// `mask & first_word == 0`, where `first_word` is the first 4 bytes of the hash in little endian.
+///
+/// The mask is exact for difficulties up to 16. Above that, the remaining words must be checked
+/// with `cerberus_verify`.
+///
+/// Returns 0 if the difficulty is invalid.
+#[no_mangle]
+pub extern "C" fn cerberus_compute_mask(difficulty: u32) -> u32 {
+    Target::from_difficulty(difficulty).map_or(0, |target| target.prefilter_mask())
+}
+
+/// Verify a solution to a challenge.
+///
+/// The hash of the solution is written to `out_hash` if it is not null, even if the solution
+/// does not meet the difficulty.
+///
+/// # Safety
+///
+/// `data` must point to `data_len` readable bytes, and `out_hash` must either be null or point to
+/// 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cerberus_verify(
+    data: *const u8,
+    data_len: usize,
+    difficulty: u32,
+    nonce: u64,
+    out_hash: *mut u8,
+) -> CerberusStatus {
+    let Some(data) = (unsafe { challenge_data(data, data_len) }) else {
+        return CerberusStatus::InvalidArgument;
+    };
+
+    unsafe { verify_result(crate::verify(data, difficulty, nonce), out_hash) }
+}
+
+/// Verify a solution to a challenge hashed with a key, see `cerberus_solve_keyed`.
+///
+/// The hash of the solution is written to `out_hash` if it is not null, even if the solution
+/// does not meet the difficulty.
+///
+/// # Safety
+///
+/// `key` must point to 32 readable bytes, `data` must point to `data_len` readable bytes, and
+/// `out_hash` must either be null or point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cerberus_verify_keyed(
+    key: *const u8,
+    data: *const u8,
+    data_len: usize,
+    difficulty: u32,
+    nonce: u64,
+    out_hash: *mut u8,
+) -> CerberusStatus {
+    let Some(key) = (unsafe { read_key(key) }) else {
+        return CerberusStatus::InvalidArgument;
+    };
+    let Some(data) = (unsafe { challenge_data(data, data_len) }) else {
+        return CerberusStatus::InvalidArgument;
+    };
+
+    unsafe { verify_result(crate::verify_keyed(&key, data, difficulty, nonce), out_hash) }
+}
+
+/// Verify a solution to a challenge hashed with the key derivation function.
+///
+/// The context must be valid UTF-8, like the challenge data. The hash of the solution is written
+/// to `out_hash` if it is not null, even if the solution does not meet the difficulty.
+///
+/// # Safety
+///
+/// `context` must point to `context_len` readable bytes, `data` must point to `data_len` readable
+/// bytes, and `out_hash` must either be null or point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cerberus_verify_derive_key(
+    context: *const u8,
+    context_len: usize,
+    data: *const u8,
+    data_len: usize,
+    difficulty: u32,
+    nonce: u64,
+    out_hash: *mut u8,
+) -> CerberusStatus {
+    let Some(context) = (unsafe { challenge_data(context, context_len) }) else {
+        return CerberusStatus::InvalidArgument;
+    };
+    let Some(data) = (unsafe { challenge_data(data, data_len) }) else {
+        return CerberusStatus::InvalidArgument;
+    };
+
+    unsafe {
+        verify_result(
+            crate::verify_derive_key(context, data, difficulty, nonce),
+            out_hash,
+        )
+    }
+}
+
+/// Solve a challenge on the calling thread.
+///
+/// # Safety
+///
+/// `data` must point to `data_len` readable bytes, `out_nonce` must point to a writable `uint64_t`,
+/// and `out_hash` must either be null or point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cerberus_solve(
+    data: *const u8,
+    data_len: usize,
+    difficulty: u32,
+    out_nonce: *mut u64,
+    out_hash: *mut u8,
+) -> CerberusStatus {
+    let Some(data) = (unsafe { challenge_data(data, data_len) }) else {
+        return CerberusStatus::InvalidArgument;
+    };
+    if out_nonce.is_null() {
+        return CerberusStatus::InvalidArgument;
+    }
+    let Some(target) = Target::from_difficulty(difficulty) else {
+        return CerberusStatus::InvalidDifficulty;
+    };
+
+    unsafe { write_solution(crate::solve_target(data, &target), out_nonce, out_hash) }
+}
+
+/// Solve a challenge hashed with a key on the calling thread.
+///
+/// Binding challenges to a per-deployment key means solutions only verify with
+/// `cerberus_verify_keyed` and the same key.
+///
+/// # Safety
+///
+/// `key` must point to 32 readable bytes, `data` must point to `data_len` readable bytes,
+/// `out_nonce` must point to a writable `uint64_t`, and `out_hash` must either be null or point to
+/// 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cerberus_solve_keyed(
+    key: *const u8,
+    data: *const u8,
+    data_len: usize,
+    difficulty: u32,
+    out_nonce: *mut u64,
+    out_hash: *mut u8,
+) -> CerberusStatus {
+    let Some(key) = (unsafe { read_key(key) }) else {
+        return CerberusStatus::InvalidArgument;
+    };
+    let Some(data) = (unsafe { challenge_data(data, data_len) }) else {
+        return CerberusStatus::InvalidArgument;
+    };
+    if out_nonce.is_null() {
+        return CerberusStatus::InvalidArgument;
+    }
+    if Target::from_difficulty(difficulty).is_none() {
+        return CerberusStatus::InvalidDifficulty;
+    }
+
+    unsafe {
+        write_solution(
+            crate::solve_keyed(&key, data, difficulty),
+            out_nonce,
+            out_hash,
+        )
+    }
+}
+
+/// Solve a challenge on multiple threads, stopping all of them at the first solution.
+///
+/// A thread count of 0 uses the available parallelism. The number of attempts made by all threads
+/// is written to `out_attempts` if it is not null.
+///
+/// # Safety
+///
+/// `data` must point to `data_len` readable bytes, `out_nonce` must point to a writable `uint64_t`,
+/// `out_hash` must either be null or point to 32 writable bytes, and `out_attempts` must either be
+/// null or point to a writable `uint64_t`.
+#[no_mangle]
+pub unsafe extern "C" fn cerberus_solve_parallel(
+    data: *const u8,
+    data_len: usize,
+    difficulty: u32,
+    threads: u32,
+    out_nonce: *mut u64,
+    out_hash: *mut u8,
+    out_attempts: *mut u64,
+) -> CerberusStatus {
+    let Some(data) = (unsafe { challenge_data(data, data_len) }) else {
+        return CerberusStatus::InvalidArgument;
+    };
+    if out_nonce.is_null() {
+        return CerberusStatus::InvalidArgument;
+    }
+    let Some(target) = Target::from_difficulty(difficulty) else {
+        return CerberusStatus::InvalidDifficulty;
+    };
+
+    let Some(solution) = crate::solve_parallel_target(data, &target, threads) else {
+        return CerberusStatus::Exhausted;
+    };
+    unsafe {
+        out_nonce.write(solution.nonce);
+        write_hash(out_hash, &solution.hash);
+        if !out_attempts.is_null() {
+            out_attempts.write(solution.attempts);
+        }
+    }
+    CerberusStatus::Ok
+}
+
+/// Solve a challenge with an arbitrary target on the calling thread.
+///
+/// `target` is the 256-bit threshold in big endian: a hash meets the target when, read as a big
+/// endian integer, it is not greater than the threshold.
+///
+/// # Safety
+///
+/// `data` must point to `data_len` readable bytes, `target` must point to 32 readable bytes,
+/// `out_nonce` must point to a writable `uint64_t`, and `out_hash` must either be null or point to
+/// 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cerberus_solve_target(
+    data: *const u8,
+    data_len: usize,
+    target: *const u8,
+    out_nonce: *mut u64,
+    out_hash: *mut u8,
+) -> CerberusStatus {
+    let Some(data) = (unsafe { challenge_data(data, data_len) }) else {
+        return CerberusStatus::InvalidArgument;
+    };
+    let Some(target) = (unsafe { read_target(target) }) else {
+        return CerberusStatus::InvalidArgument;
+    };
+    if out_nonce.is_null() {
+        return CerberusStatus::InvalidArgument;
+    }
+
+    unsafe { write_solution(crate::solve_target(data, &target), out_nonce, out_hash) }
+}
+
+/// Verify a solution to a challenge with an arbitrary target.
+///
+/// `target` is encoded as in `cerberus_solve_target`. The hash of the solution is written to
+/// `out_hash` if it is not null, even if the solution does not meet the target.
+///
+/// # Safety
+///
+/// `data` must point to `data_len` readable bytes, `target` must point to 32 readable bytes, and
+/// `out_hash` must either be null or point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cerberus_verify_target(
+    data: *const u8,
+    data_len: usize,
+    target: *const u8,
+    nonce: u64,
+    out_hash: *mut u8,
+) -> CerberusStatus {
+    let Some(data) = (unsafe { challenge_data(data, data_len) }) else {
+        return CerberusStatus::InvalidArgument;
+    };
+    let Some(target) = (unsafe { read_target(target) }) else {
+        return CerberusStatus::InvalidArgument;
+    };
+
+    unsafe { verify_result(crate::verify_target(data, &target, nonce), out_hash) }
+}
+
+/// Read a big endian target from C.
+///
+/// # Safety
+///
+/// `target` must either be null or point to 32 readable bytes.
+unsafe fn read_target(target: *const u8) -> Option<Target> {
+    if target.is_null() {
+        return None;
+    }
+    Some(Target::from_be_bytes(unsafe {
+        target.cast::<[u8; 32]>().read_unaligned()
+    }))
+}
+
+/// Read a key from C.
+///
+/// # Safety
+///
+/// `key` must either be null or point to 32 readable bytes.
+unsafe fn read_key(key: *const u8) -> Option<[u8; 32]> {
+    if key.is_null() {
+        return None;
+    }
+    Some(unsafe { key.cast::<[u8; 32]>().read_unaligned() })
+}
+
+/// # Safety
+///
+/// `out_nonce` must point to a writable `uint64_t`, and `out_hash` must either be null or point to
+/// 32 writable bytes.
+unsafe fn write_solution(
+    solution: Option<(u64, crate::Hash)>,
+    out_nonce: *mut u64,
+    out_hash: *mut u8,
+) -> CerberusStatus {
+    let Some((nonce, hash)) = solution else {
+        return CerberusStatus::Exhausted;
+    };
+    unsafe {
+        out_nonce.write(nonce);
+        write_hash(out_hash, &hash);
+    }
+    CerberusStatus::Ok
+}
+
+/// # Safety
+///
+/// `out_hash` must either be null or point to 32 writable bytes.
+unsafe fn verify_result(
+    result: Result<crate::Hash, VerifyError>,
+    out_hash: *mut u8,
+) -> CerberusStatus {
+    match result {
+        Ok(hash) => {
+            unsafe { write_hash(out_hash, &hash) };
+            CerberusStatus::Ok
+        }
+        Err(VerifyError::InsufficientWork(hash)) => {
+            unsafe { write_hash(out_hash, &hash) };
+            CerberusStatus::InsufficientWork
+        }
+        Err(VerifyError::InvalidDifficulty(_)) => CerberusStatus::InvalidDifficulty,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi_roundtrip() {
+        let data = b"Accept-Language=en,X-Real-IP=127.0.0.1";
+
+        assert_eq!(cerberus_compute_mask(0), 0);
+        assert_eq!(cerberus_compute_mask(4), 0xff);
+
+        let mut nonce = 0u64;
+        let mut hash = [0u8; 32];
+        let status =
+            unsafe { cerberus_solve(data.as_ptr(), data.len(), 4, &mut nonce, hash.as_mut_ptr()) };
+        assert_eq!(status, CerberusStatus::Ok);
+        assert_eq!(hash[0], 0);
+
+        let mut verified = [0u8; 32];
+        let status =
+            unsafe { cerberus_verify(data.as_ptr(), data.len(), 4, nonce, verified.as_mut_ptr()) };
+        assert_eq!(status, CerberusStatus::Ok);
+        assert_eq!(hash, verified);
+
+        let mut ref_hasher = ::blake3::Hasher::new();
+        ref_hasher.update(::blake3::hash(data).to_hex().as_bytes());
+        ref_hasher.update(&nonce.rotate_left(32).to_le_bytes());
+        assert_eq!(&hash, ref_hasher.finalize().as_bytes());
+    }
+
+    #[test]
+    fn test_ffi_rejects() {
+        let data = b"Accept-Language=en,X-Real-IP=127.0.0.1";
+        let mut nonce = 0u64;
+
+        let status =
+            unsafe { cerberus_verify(data.as_ptr(), data.len(), 0, 0, core::ptr::null_mut()) };
+        assert_eq!(status, CerberusStatus::InvalidDifficulty);
+
+        let invalid_utf8 = [0xffu8, 0xfe];
+        let status =
+            unsafe { cerberus_verify(invalid_utf8.as_ptr(), 2, 4, 0, core::ptr::null_mut()) };
+        assert_eq!(status, CerberusStatus::InvalidArgument);
+
+        let status =
+            unsafe { cerberus_solve(core::ptr::null(), 4, 4, &mut nonce, core::ptr::null_mut()) };
+        assert_eq!(status, CerberusStatus::InvalidArgument);
+
+        let status = unsafe {
+            cerberus_solve(
+                data.as_ptr(),
+                data.len(),
+                4,
+                core::ptr::null_mut(),
+                core::ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, CerberusStatus::InvalidArgument);
+
+        unsafe {
+            cerberus_solve(
+                data.as_ptr(),
+                data.len(),
+                4,
+                &mut nonce,
+                core::ptr::null_mut(),
+            )
+        };
+        let mut found = 0;
+        for tampered in [nonce ^ 1, nonce ^ 2, nonce ^ 4] {
+            let status = unsafe {
+                cerberus_verify(
+                    data.as_ptr(),
+                    data.len(),
+                    4,
+                    tampered,
+                    core::ptr::null_mut(),
+                )
+            };
+            if status == CerberusStatus::InsufficientWork {
+                found += 1;
+            }
+        }
+        assert!(found > 0);
+    }
+
+    #[test]
+    fn test_ffi_parallel() {
+        let data = b"Accept-Language=en,X-Real-IP=127.0.0.1";
+
+        let mut nonce = 0u64;
+        let mut attempts = 0u64;
+        let status = unsafe {
+            cerberus_solve_parallel(
+                data.as_ptr(),
+                data.len(),
+                5,
+                4,
+                &mut nonce,
+                core::ptr::null_mut(),
+                &mut attempts,
+            )
+        };
+        assert_eq!(status, CerberusStatus::Ok);
+        assert!(attempts > 0);
+
+        let status =
+            unsafe { cerberus_verify(data.as_ptr(), data.len(), 5, nonce, core::ptr::null_mut()) };
+        assert_eq!(status, CerberusStatus::Ok);
+    }
+
+    #[test]
+    fn test_ffi_keyed() {
+        let data = b"Accept-Language=en,X-Real-IP=127.0.0.1";
+        let key = [0x42u8; 32];
+
+        let mut nonce = 0u64;
+        let mut hash = [0u8; 32];
+        let status = unsafe {
+            cerberus_solve_keyed(
+                key.as_ptr(),
+                data.as_ptr(),
+                data.len(),
+                4,
+                &mut nonce,
+                hash.as_mut_ptr(),
+            )
+        };
+        assert_eq!(status, CerberusStatus::Ok);
+        assert_eq!(hash[0], 0);
+
+        let mut verified = [0u8; 32];
+        let status = unsafe {
+            cerberus_verify_keyed(
+                key.as_ptr(),
+                data.as_ptr(),
+                data.len(),
+                4,
+                nonce,
+                verified.as_mut_ptr(),
+            )
+        };
+        assert_eq!(status, CerberusStatus::Ok);
+        assert_eq!(hash, verified);
+
+        let context = b"cerberus 2024-01-01 test context";
+        let (derived, _) =
+            crate::solve_derive_key("cerberus 2024-01-01 test context", "", 4).unwrap();
+        let status = unsafe {
+            cerberus_verify_derive_key(
+                context.as_ptr(),
+                context.len(),
+                core::ptr::null(),
+                0,
+                4,
+                derived,
+                core::ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, CerberusStatus::Ok);
+
+        let status = unsafe {
+            cerberus_solve_keyed(
+                core::ptr::null(),
+                data.as_ptr(),
+                data.len(),
+                4,
+                &mut nonce,
+                core::ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, CerberusStatus::InvalidArgument);
+        let status = unsafe {
+            cerberus_verify_keyed(
+                key.as_ptr(),
+                data.as_ptr(),
+                data.len(),
+                0,
+                nonce,
+                core::ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, CerberusStatus::InvalidDifficulty);
+    }
+
+    #[test]
+    fn test_ffi_target() {
+        let data = b"Accept-Language=en,X-Real-IP=127.0.0.1";
+        let target = Target::from_expected_attempts(3000).to_be_bytes();
+
+        let mut nonce = 0u64;
+        let mut hash = [0u8; 32];
+        let status = unsafe {
+            cerberus_solve_target(
+                data.as_ptr(),
+                data.len(),
+                target.as_ptr(),
+                &mut nonce,
+                hash.as_mut_ptr(),
+            )
+        };
+        assert_eq!(status, CerberusStatus::Ok);
+        assert!(hash <= target);
+
+        let status = unsafe {
+            cerberus_verify_target(
+                data.as_ptr(),
+                data.len(),
+                target.as_ptr(),
+                nonce,
+                core::ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, CerberusStatus::Ok);
+
+        let status = unsafe {
+            cerberus_verify_target(
+                data.as_ptr(),
+                data.len(),
+                core::ptr::null(),
+                nonce,
+                core::ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, CerberusStatus::InvalidArgument);
+    }
+}
@@ -1,20 +1,49 @@
 mod utils;
+use core::ops::ControlFlow;
 use serde::Serialize;
 use solver::Solver;
 use utils::set_panic_hook;
 use wasm_bindgen::prelude::*;
-use web_sys::DedicatedWorkerGlobalScope;
 
 mod blake3;
+pub use blake3::{Hasher, OutputReader};
 
 mod solver;
 
+#[macro_use]
+mod backend;
+pub use backend::Backend;
+
+mod target;
+pub use target::Target;
+
+mod resume;
+use resume::Search;
+pub use resume::{solve_budget, Checkpoint, SolveStatus};
+
+mod protocol;
+pub use protocol::{PageMessage, WorkerMessage, PROTOCOL_VERSION};
+
+mod progress;
+pub use progress::PROGRESS_INTERVAL_MS;
+use progress::{now_ms, ProgressMeter};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ffi;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod parallel;
+#[cfg(not(target_arch = "wasm32"))]
+pub use parallel::{solve_parallel, solve_parallel_backend, solve_parallel_target, Solution};
+
 #[cold]
 fn unlikely() {}
 
 /// Compute a mask for a Cerberus PoW (mask & V[0] == 0)
+///
+/// Difficulties of 16 and above cannot be checked by the first word alone, use [`Target`] instead.
 pub const fn compute_mask_cerberus(difficulty_factor: core::num::NonZeroU8) -> u32 {
-    if difficulty_factor.get() == 16 {
+    if difficulty_factor.get() >= 16 {
         return !0;
     }
     // Cerberus compares output as if it was big endian, but BLAKE3 outputs little endian
@@ -22,10 +51,32 @@ pub const fn compute_mask_cerberus(difficulty_factor: core::num::NonZeroU8) -> u
     !(!0u32 >> (difficulty_factor.get() * 2)).swap_bytes()
 }
 
-#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+#[cfg(all(
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    not(feature = "interleave")
+))]
 pub type CerberusSolver = solver::simd128::CerberusSolver;
-#[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+#[cfg(all(
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    feature = "interleave"
+))]
+pub type CerberusSolver = solver::simd128::CerberusSolver<2>;
+#[cfg(target_arch = "aarch64")]
+pub type CerberusSolver = solver::neon::CerberusSolver;
+#[cfg(not(any(
+    all(target_arch = "wasm32", target_feature = "simd128"),
+    all(target_arch = "wasm32", feature = "interleave-scalar"),
+    target_arch = "aarch64"
+)))]
 pub type CerberusSolver = solver::scalar::CerberusSolver;
+#[cfg(all(
+    target_arch = "wasm32",
+    not(target_feature = "simd128"),
+    feature = "interleave-scalar"
+))]
+pub type CerberusSolver = solver::scalar::CerberusSolver<2>;
 
 /// Encode a blake3 hash into hex
 fn encode_hex_le(out: &mut [u8; 64], inp: [u32; 8]) {
@@ -48,16 +99,394 @@ fn encode_hex_le(out: &mut [u8; 64], inp: [u32; 8]) {
     }
 }
 
-fn worker_global_scope() -> DedicatedWorkerGlobalScope {
-    let global = js_sys::global();
-    global.dyn_into().expect("not running in a web worker")
+/// Where [`process_task`] posts progress and results
+struct Outbox {
+    post: js_sys::Function,
+    this: JsValue,
+}
+
+impl Outbox {
+    /// Post through `callback`, or the `postMessage` of the global scope, which dedicated web
+    /// workers and Deno workers have.
+    fn new(callback: Option<js_sys::Function>) -> Result<Self, JsError> {
+        if let Some(post) = callback {
+            return Ok(Self {
+                post,
+                this: JsValue::UNDEFINED,
+            });
+        }
+        let global = js_sys::global();
+        let post = js_sys::Reflect::get(&global, &JsValue::from_str("postMessage"))
+            .ok()
+            .and_then(|post| post.dyn_into().ok())
+            .ok_or_else(|| JsError::new("no postMessage in the global scope, pass a callback"))?;
+        Ok(Self {
+            post,
+            this: global.into(),
+        })
+    }
+
+    fn post(&self, message: &WorkerMessage) -> Result<(), JsValue> {
+        let message = serde_wasm_bindgen::to_value(message)?;
+        self.post.call1(&self.this, &message).map(drop)
+    }
+}
+
+/// A BLAKE3 output, as little endian words
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hash(pub [u32; 8]);
+
+impl Hash {
+    /// Encode the hash into lowercase hex, the format the server compares against
+    pub fn to_hex(&self) -> String {
+        let mut out = [0; 64];
+        encode_hex_le(&mut out, self.0);
+        String::from_utf8(out.to_vec()).unwrap()
+    }
+
+    /// The hash as bytes, in the order BLAKE3 outputs them
+    pub fn to_bytes(&self) -> [u8; 32] {
+        core::array::from_fn(|i| self.0[i / 4].to_le_bytes()[i % 4])
+    }
+}
+
+/// Reasons a solution can be rejected by [`verify`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The difficulty is outside the range the solvers support
+    InvalidDifficulty(u32),
+    /// The hash of the solution does not meet the difficulty
+    InsufficientWork(Hash),
+}
+
+impl core::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidDifficulty(difficulty) => write!(f, "invalid difficulty: {difficulty}"),
+            Self::InsufficientWork(hash) => {
+                write!(f, "hash {} does not meet the difficulty", hash.to_hex())
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Reasons a prefix cannot start a [`CerberusMessage`].
+///
+/// The solvers hash the nonce as the whole trailing block of the first chunk and as the root node,
+/// so only prefixes of whole BLAKE3 blocks that leave room for that block are supported: 64 to
+/// [`CerberusMessage::MAX_PREFIX_LEN`] bytes in steps of 64. Hash longer or unaligned data into a
+/// fixed-length prefix first, as the challenge functions do with the hex encoded hash of the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefixError {
+    /// The prefix is empty
+    Empty,
+    /// The length of the prefix is not a multiple of the 64-byte BLAKE3 block
+    Unaligned(usize),
+    /// The prefix leaves no room for the trailing block in the first 1024-byte chunk
+    TooLong(usize),
+}
+
+impl core::fmt::Display for PrefixError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the prefix is empty"),
+            Self::Unaligned(len) => write!(f, "prefix length {len} is not a multiple of 64"),
+            Self::TooLong(len) => write!(
+                f,
+                "prefix length {len} exceeds {}",
+                CerberusMessage::MAX_PREFIX_LEN
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PrefixError {}
+
+/// Reasons [`process_task`] stops without a solution, posted to the page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskError {
+    /// The task is not a [`PageMessage::Task`]
+    InvalidTask,
+    /// The difficulty is outside the range the solvers support
+    InvalidDifficulty(u32),
+    /// The work was split between zero threads
+    NoThreads,
+    /// The thread ID is not below the number of threads
+    InvalidThreadId { thread_id: u32, threads: u32 },
+    /// Every batch ID of the thread has been tried
+    Exhausted,
+}
+
+impl TaskError {
+    /// A stable identifier of the error kind, for the page to tell errors apart
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidTask => "invalid-task",
+            Self::InvalidDifficulty(_) => "invalid-difficulty",
+            Self::NoThreads => "no-threads",
+            Self::InvalidThreadId { .. } => "invalid-thread-id",
+            Self::Exhausted => "exhausted",
+        }
+    }
+}
+
+impl core::fmt::Display for TaskError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidTask => write!(f, "the task is not a valid task message"),
+            Self::InvalidDifficulty(difficulty) => write!(f, "invalid difficulty: {difficulty}"),
+            Self::NoThreads => write!(f, "the number of threads must not be zero"),
+            Self::InvalidThreadId { thread_id, threads } => {
+                write!(
+                    f,
+                    "thread ID {thread_id} is out of range for {threads} threads"
+                )
+            }
+            Self::Exhausted => write!(f, "the key space is exhausted without a solution"),
+        }
+    }
+}
+
+impl std::error::Error for TaskError {}
+
+/// Compute the salt of a challenge, which is the hex encoded BLAKE3 hash of the challenge data
+fn challenge_salt(data: &str) -> [u8; 64] {
+    let mut salt = [0; 64];
+    encode_hex_le(&mut salt, blake3::hash(data.as_bytes()));
+    salt
+}
+
+/// The hash function a challenge is solved and verified with
+#[derive(Debug, Clone, Copy)]
+enum Mode<'a> {
+    Hash,
+    Keyed(&'a [u8; 32]),
+    DeriveKey(&'a str),
 }
 
-#[derive(Debug, Serialize)]
-struct Resp {
-    hash: String,
+impl Mode<'_> {
+    fn message(self, salt: &[u8; 64], batch_id: u32) -> CerberusMessage {
+        match self {
+            Self::Hash => CerberusMessage::new(salt, batch_id),
+            Self::Keyed(key) => CerberusMessage::new_keyed(key, salt, batch_id),
+            Self::DeriveKey(context) => CerberusMessage::new_derive_key(context, salt, batch_id),
+        }
+        .expect("salt is always a valid prefix")
+    }
+}
+
+/// Solve a Cerberus challenge on the current thread.
+///
+/// Returns the nonce, in the format reported by [`process_task`], and its hash.
+/// Returns None when the difficulty is invalid or the key space is exhausted.
+pub fn solve(data: &str, difficulty: u32) -> Option<(u64, Hash)> {
+    solve_target(data, &Target::from_difficulty(difficulty)?)
+}
+
+/// Solve a Cerberus challenge with an arbitrary target on the current thread.
+///
+/// Uses the backend from [`Backend::detect`].
+pub fn solve_target(data: &str, target: &Target) -> Option<(u64, Hash)> {
+    with_detected_backend!(S => solve_mode_with::<S>(Mode::Hash, data, target))
+}
+
+/// Solve a Cerberus challenge hashed with the keyed hash function, see
+/// [`CerberusMessage::new_keyed`].
+///
+/// The solution only verifies with [`verify_keyed`] and the same key.
+pub fn solve_keyed(key: &[u8; 32], data: &str, difficulty: u32) -> Option<(u64, Hash)> {
+    let target = Target::from_difficulty(difficulty)?;
+    with_detected_backend!(S => solve_mode_with::<S>(Mode::Keyed(key), data, &target))
+}
+
+/// Solve a Cerberus challenge hashed with the key derivation function, see
+/// [`CerberusMessage::new_derive_key`].
+///
+/// The solution only verifies with [`verify_derive_key`] and the same context.
+pub fn solve_derive_key(context: &str, data: &str, difficulty: u32) -> Option<(u64, Hash)> {
+    let target = Target::from_difficulty(difficulty)?;
+    with_detected_backend!(S => solve_mode_with::<S>(Mode::DeriveKey(context), data, &target))
+}
+
+fn solve_mode_with<S: Solver + From<CerberusMessage>>(
+    mode: Mode,
+    data: &str,
+    target: &Target,
+) -> Option<(u64, Hash)> {
+    let salt = challenge_salt(data);
+
+    for set in 0..=u32::MAX {
+        let mut solver = S::from(mode.message(&salt, set));
+        if let Some((nonce, hash)) = solver.solve(target, |_| ControlFlow::Continue(())) {
+            return Some((nonce[1] as u64 | (nonce[0] as u64) << 32, Hash(hash)));
+        }
+    }
+
+    None
+}
+
+/// Verify a solution to a Cerberus challenge.
+///
+/// The nonce is in the same format as reported by [`process_task`]: the batch ID in the upper
+/// 32 bits and the nonce within the batch in the lower 32 bits.
+///
+/// Returns the hash of the solution if it meets the difficulty as the server checks it, see
+/// [`Target::from_answer_difficulty`].
+pub fn verify(data: &str, difficulty: u32, nonce: u64) -> Result<Hash, VerifyError> {
+    verify_mode(Mode::Hash, data, difficulty, nonce)
+}
+
+/// Verify a solution to a Cerberus challenge hashed with the keyed hash function, see
+/// [`solve_keyed`].
+///
+/// Solutions to the same challenge with any other key, or without one, are rejected.
+pub fn verify_keyed(
+    key: &[u8; 32],
+    data: &str,
+    difficulty: u32,
+    nonce: u64,
+) -> Result<Hash, VerifyError> {
+    verify_mode(Mode::Keyed(key), data, difficulty, nonce)
+}
+
+/// Verify a solution to a Cerberus challenge hashed with the key derivation function, see
+/// [`solve_derive_key`].
+pub fn verify_derive_key(
+    context: &str,
+    data: &str,
     difficulty: u32,
     nonce: u64,
+) -> Result<Hash, VerifyError> {
+    verify_mode(Mode::DeriveKey(context), data, difficulty, nonce)
+}
+
+fn verify_mode(mode: Mode, data: &str, difficulty: u32, nonce: u64) -> Result<Hash, VerifyError> {
+    let target = Target::from_answer_difficulty(difficulty)
+        .ok_or(VerifyError::InvalidDifficulty(difficulty))?;
+    verify_target_mode(mode, data, &target, nonce)
+}
+
+/// Verify a solution to a Cerberus challenge with an arbitrary target.
+pub fn verify_target(data: &str, target: &Target, nonce: u64) -> Result<Hash, VerifyError> {
+    verify_target_mode(Mode::Hash, data, target, nonce)
+}
+
+fn verify_target_mode(
+    mode: Mode,
+    data: &str,
+    target: &Target,
+    nonce: u64,
+) -> Result<Hash, VerifyError> {
+    let message = mode.message(&challenge_salt(data), (nonce >> 32) as u32);
+    let hash = Hash(message.hash(nonce as u32));
+
+    if !target.is_met(&hash.0) {
+        return Err(VerifyError::InsufficientWork(hash));
+    }
+
+    Ok(hash)
+}
+
+/// Verify a solution before submitting it, returning the hex encoded hash.
+#[wasm_bindgen(js_name = verify)]
+pub fn verify_hex(data: &str, difficulty: u32, nonce: u64) -> Result<String, JsError> {
+    Ok(verify(data, difficulty, nonce)?.to_hex())
+}
+
+/// The name of the backend [`process_task`] solves with, for diagnostics.
+#[wasm_bindgen(js_name = backend)]
+pub fn backend_name() -> String {
+    Backend::detect().name().to_string()
+}
+
+/// A solution, in the shape the page submits it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Resp {
+    /// The hex encoded hash
+    pub hash: String,
+    /// The difficulty the challenge was solved at
+    pub difficulty: u32,
+    /// The nonce, with the batch ID in the upper 32 bits
+    pub nonce: u64,
+}
+
+/// A challenge solved a slice at a time on the calling thread.
+///
+/// Unlike [`process_task`], it neither blocks until done nor needs a dedicated worker, so it can
+/// run on the main thread, in service or shared workers, Node or Deno, yielding between slices.
+#[wasm_bindgen(js_name = Solver)]
+pub struct SteppedSolver {
+    search: Search,
+    difficulty: u32,
+    target: Target,
+    status: SolveStatus,
+    meter: ProgressMeter,
+}
+
+#[wasm_bindgen(js_class = Solver)]
+impl SteppedSolver {
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: String, difficulty: u32) -> Result<SteppedSolver, JsError> {
+        let target = Target::from_difficulty(difficulty)
+            .ok_or(VerifyError::InvalidDifficulty(difficulty))?;
+        Ok(Self {
+            search: Search::new(&data),
+            difficulty,
+            status: SolveStatus::BudgetSpent(Checkpoint::default()),
+            meter: ProgressMeter::new(&target, 1, now_ms()),
+            target,
+        })
+    }
+
+    /// Try about `iterations` more nonces.
+    ///
+    /// Returns a [`WorkerMessage`]: progress while unsolved, then the solution, or an error
+    /// once the key space is exhausted.
+    #[wasm_bindgen(unchecked_return_type = "WorkerMessage")]
+    pub fn step(&mut self, iterations: u32) -> Result<JsValue, JsError> {
+        let message = match self.advance(iterations.into()) {
+            SolveStatus::Found(nonce, hash) => WorkerMessage::solved(self.difficulty, nonce, hash),
+            SolveStatus::Exhausted => TaskError::Exhausted.into(),
+            SolveStatus::BudgetSpent(_) => {
+                let now = now_ms();
+                self.meter
+                    .record(iterations, now)
+                    .unwrap_or_else(|| self.meter.report(now))
+            }
+        };
+        Ok(serde_wasm_bindgen::to_value(&message)?)
+    }
+
+    /// Where the search continues, to resume it elsewhere. Undefined once it has finished.
+    #[wasm_bindgen(getter)]
+    pub fn checkpoint(&self) -> Result<JsValue, JsError> {
+        match self.status {
+            SolveStatus::BudgetSpent(checkpoint) => Ok(serde_wasm_bindgen::to_value(&checkpoint)?),
+            _ => Ok(JsValue::UNDEFINED),
+        }
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_checkpoint(&mut self, checkpoint: JsValue) -> Result<(), JsError> {
+        let checkpoint: Checkpoint = serde_wasm_bindgen::from_value(checkpoint)?;
+        if checkpoint.stride == 0 {
+            return Err(JsError::new("the stride of a checkpoint must not be zero"));
+        }
+        self.status = SolveStatus::BudgetSpent(checkpoint);
+        Ok(())
+    }
+}
+
+impl SteppedSolver {
+    /// Run one slice of the search, once it has finished returning how it did.
+    fn advance(&mut self, budget: u64) -> SolveStatus {
+        if let SolveStatus::BudgetSpent(checkpoint) = self.status {
+            self.status = self.search.solve_budget(&self.target, checkpoint, budget);
+        }
+        self.status
+    }
 }
 
 #[wasm_bindgen(start)]
@@ -70,85 +499,659 @@ fn start() {
 pub struct CerberusMessage {
     pub(crate) midstate: [u32; 8],
     pub(crate) batch_id: u32,
+    /// The flags of the hash mode, set on every block
+    pub(crate) flags: u32,
 }
 
 impl CerberusMessage {
+    /// The longest prefix supported, leaving one block of the first chunk for the trailing block
+    pub const MAX_PREFIX_LEN: usize = blake3::CHUNK_LEN - blake3::BLOCK_LEN;
+
     /// The flags for the trailing block
     pub const fn trailing_block_flags(&self) -> u32 {
-        blake3::FLAG_CHUNK_END | blake3::FLAG_ROOT
+        self.flags | blake3::FLAG_CHUNK_END | blake3::FLAG_ROOT
     }
 
     /// Create a new Ceberus message
-    pub fn new(salt: &[u8; 64], thread_id: u32) -> Option<Self> {
-        let mut init_block = [0; 16];
-        for i in 0..16 {
-            init_block[i] = u32::from_le_bytes([
-                salt[i * 4],
-                salt[i * 4 + 1],
-                salt[i * 4 + 2],
-                salt[i * 4 + 3],
-            ]);
-        }
-
-        let midstate = blake3::compress8(
-            &blake3::IV,
-            &init_block,
-            0,
-            salt.len() as u32,
-            blake3::FLAG_CHUNK_START,
-        );
+    ///
+    /// Only prefixes of 64 to [`Self::MAX_PREFIX_LEN`] bytes in steps of 64 are supported, see
+    /// [`PrefixError`]. Arbitrary-length data has to be hashed into such a prefix first.
+    pub fn new(prefix: &[u8], thread_id: u32) -> Result<Self, PrefixError> {
+        Self::new_internal(blake3::IV, 0, prefix, thread_id)
+    }
+
+    /// Create a new Cerberus message hashed with the keyed hash function.
+    ///
+    /// Binding puzzles to a per-deployment secret means solutions are worthless for any other
+    /// deployment, see [`solve_keyed`] and [`verify_keyed`]. The prefix has the same restrictions
+    /// as in [`CerberusMessage::new`].
+    pub fn new_keyed(key: &[u8; 32], prefix: &[u8], thread_id: u32) -> Result<Self, PrefixError> {
+        Self::new_internal(
+            blake3::key_words(key),
+            blake3::FLAG_KEYED_HASH,
+            prefix,
+            thread_id,
+        )
+    }
 
-        Some(Self {
-            midstate,
+    /// Create a new Cerberus message hashed with the key derivation function, using the prefix
+    /// and nonce as key material.
+    ///
+    /// See [`solve_derive_key`] and [`verify_derive_key`]. The prefix has the same restrictions
+    /// as in [`CerberusMessage::new`].
+    pub fn new_derive_key(
+        context: &str,
+        prefix: &[u8],
+        thread_id: u32,
+    ) -> Result<Self, PrefixError> {
+        Self::new_internal(
+            blake3::Hasher::derive_key_context_key(context),
+            blake3::FLAG_DERIVE_KEY_MATERIAL,
+            prefix,
+            thread_id,
+        )
+    }
+
+    fn new_internal(
+        key_words: [u32; 8],
+        flags: u32,
+        prefix: &[u8],
+        thread_id: u32,
+    ) -> Result<Self, PrefixError> {
+        if prefix.is_empty() {
+            return Err(PrefixError::Empty);
+        }
+        if !prefix.len().is_multiple_of(blake3::BLOCK_LEN) {
+            return Err(PrefixError::Unaligned(prefix.len()));
+        }
+        if prefix.len() > Self::MAX_PREFIX_LEN {
+            return Err(PrefixError::TooLong(prefix.len()));
+        }
+
+        let mut chunk = blake3::ChunkState::new(key_words, 0, flags);
+        chunk.update(prefix);
+        chunk.compress_block();
+
+        Ok(Self {
+            midstate: chunk.chaining_value(),
             batch_id: thread_id,
+            flags,
         })
     }
+
+    /// Read extended output of the message with the given nonce.
+    ///
+    /// The first 32 bytes are the hash, everything after can be used to derive more values from
+    /// a solved puzzle.
+    pub fn xof(&self, nonce: u32) -> OutputReader {
+        let mut msg = [0; 16];
+        msg[0] = self.batch_id;
+        msg[1] = nonce;
+        OutputReader::new(blake3::Output::new(
+            self.midstate,
+            msg,
+            0,
+            8,
+            self.trailing_block_flags() & !blake3::FLAG_ROOT,
+        ))
+    }
+
+    /// Hash the message with the given nonce
+    pub fn hash(&self, nonce: u32) -> [u32; 8] {
+        let mut msg = [0; 16];
+        msg[0] = self.batch_id;
+        msg[1] = nonce;
+        blake3::compress8(&self.midstate, &msg, 0, 8, self.trailing_block_flags())
+    }
 }
 
+#[wasm_bindgen(typescript_custom_section)]
+const TASK_OPTIONS: &str = r#"
+/** The optional arguments of `process_task` */
+export interface TaskOptions {
+    cancel?: Int32Array;
+    post?: (message: WorkerMessage) => void;
+    progress?: BigInt64Array;
+    batches?: BigInt64Array;
+}
+"#;
+
 #[wasm_bindgen]
-pub fn process_task(data: &str, difficulty: u32, thread_id: u32, threads: u32) {
-    let worker = worker_global_scope();
+extern "C" {
+    /// The optional arguments of [`process_task`], a plain object from the page
+    #[wasm_bindgen(typescript_type = "TaskOptions")]
+    pub type TaskOptions;
 
-    let mask =
-        compute_mask_cerberus(core::num::NonZeroU8::new(difficulty.try_into().unwrap()).unwrap());
+    #[wasm_bindgen(method, getter)]
+    fn cancel(this: &TaskOptions) -> Option<js_sys::Int32Array>;
 
-    let mut set = thread_id;
+    #[wasm_bindgen(method, getter)]
+    fn post(this: &TaskOptions) -> Option<js_sys::Function>;
 
-    let salt_hex = ::blake3::hash(data.as_bytes()).to_hex();
+    #[wasm_bindgen(method, getter)]
+    fn progress(this: &TaskOptions) -> Option<js_sys::BigInt64Array>;
 
-    loop {
-        let Some(message) = CerberusMessage::new(salt_hex.as_bytes().try_into().unwrap(), set)
-        else {
-            return;
-        };
+    #[wasm_bindgen(method, getter)]
+    fn batches(this: &TaskOptions) -> Option<js_sys::BigInt64Array>;
+}
+
+/// Solve a challenge in a worker, posting progress and the solution to the page.
+///
+/// Takes a [`PageMessage::Task`] and posts [`WorkerMessage`]s: a hello first, then progress every
+/// [`PROGRESS_INTERVAL_MS`], and finally the solution or why there is none. They go to
+/// `options.post` when given, as needed in Node `worker_threads` and service workers, and to the
+/// `postMessage` of the global scope otherwise. Failures to post at all are thrown.
+///
+/// When `options.cancel` is given, its first element is checked every few thousand attempts and
+/// the task stops as cancelled once it is non-zero. Backed by a `SharedArrayBuffer`, this lets the
+/// page stop a solve without terminating the worker, so it can be reused.
+///
+/// When `options.progress` is given, attempts are instead added to its element at the thread ID
+/// as they are made, and no progress messages are posted. The page polls the counters of all
+/// threads from a shared buffer rather than handling a message per report.
+///
+/// When `options.batches` is given, its first element is a cursor shared by all threads, and each
+/// claims the next batch ID from it whenever it needs one. Otherwise thread `thread_id` of
+/// `threads` takes every `threads`-th batch ID starting from its own, so a slow thread holds up
+/// its share.
+#[wasm_bindgen]
+pub fn process_task(
+    #[wasm_bindgen(unchecked_param_type = "PageMessage")] task: JsValue,
+    options: Option<TaskOptions>,
+) -> Result<(), JsValue> {
+    let outbox = Outbox::new(options.as_ref().and_then(TaskOptions::post))?;
+    outbox.post(&WorkerMessage::hello(Backend::detect()))?;
+    let Ok(PageMessage::Task {
+        data,
+        difficulty,
+        thread_id,
+        threads,
+    }) = serde_wasm_bindgen::from_value(task)
+    else {
+        return outbox.post(&TaskError::InvalidTask.into());
+    };
+    let shared = SharedBuffers {
+        cancel: options.as_ref().and_then(TaskOptions::cancel),
+        progress: options.as_ref().and_then(TaskOptions::progress),
+        batches: options.as_ref().and_then(TaskOptions::batches),
+    };
+    let message = run_task(&outbox, &shared, &data, difficulty, thread_id, threads)?;
+    outbox.post(&message)
+}
+
+/// The batch ID taken by a claim on a cursor shared between threads, given the count of claims
+/// before it. None once the batch IDs have run out.
+///
+/// Cursors count claims in 64 bits, so claims past the last batch ID do not wrap around.
+pub(crate) fn claimed_batch(claims: impl TryInto<u32>) -> Option<u32> {
+    claims.try_into().ok()
+}
+
+/// The memory [`process_task`] may share with the page and the other threads
+struct SharedBuffers {
+    cancel: Option<js_sys::Int32Array>,
+    progress: Option<js_sys::BigInt64Array>,
+    batches: Option<js_sys::BigInt64Array>,
+}
+
+impl SharedBuffers {
+    fn is_cancelled(&self) -> Result<bool, JsValue> {
+        match &self.cancel {
+            Some(cancel) => js_sys::Atomics::load(cancel, 0).map(|flag| flag != 0),
+            None => Ok(false),
+        }
+    }
+
+    /// The batch ID to search after `last`, or the first one. None once they have run out.
+    fn next_batch(
+        &self,
+        last: Option<u32>,
+        thread_id: u32,
+        threads: u32,
+    ) -> Result<Option<u32>, JsValue> {
+        match (&self.batches, last) {
+            (Some(batches), _) => Ok(claimed_batch(js_sys::Atomics::add_bigint(batches, 0, 1)?)),
+            (None, None) => Ok(Some(thread_id)),
+            (None, Some(last)) => Ok(last.checked_add(threads)),
+        }
+    }
+}
+
+fn run_task(
+    outbox: &Outbox,
+    shared: &SharedBuffers,
+    data: &str,
+    difficulty: u32,
+    thread_id: u32,
+    threads: u32,
+) -> Result<WorkerMessage, JsValue> {
+    let Some(target) = Target::from_difficulty(difficulty) else {
+        return Ok(TaskError::InvalidDifficulty(difficulty).into());
+    };
+    if threads == 0 {
+        return Ok(TaskError::NoThreads.into());
+    }
+    if thread_id >= threads {
+        return Ok(TaskError::InvalidThreadId { thread_id, threads }.into());
+    }
+
+    let mut meter = ProgressMeter::new(&target, threads, now_ms());
+    let salt = challenge_salt(data);
+    let mut set = None;
+    while let Some(next) = shared.next_batch(set, thread_id, threads)? {
+        set = Some(next);
+        let message = CerberusMessage::new(&salt, next).expect("salt is always a valid prefix");
         let mut solver = CerberusSolver::from(message);
         solver.set_report_slot(thread_id, threads);
 
-        let Some((nonce, hash)) = solver.solve(mask, |nonce| {
-            worker
-                .post_message(&JsValue::from_f64(f64::from(nonce)))
-                .expect("Failed to send message");
-        }) else {
-            if let Some(new_set) = set.checked_add(threads) {
-                set = new_set;
-                continue;
-            } else {
-                return;
+        let mut stopped = Ok(false);
+        let solution = solver.solve(&target, |attempts| {
+            let reported = match &shared.progress {
+                Some(progress) => {
+                    js_sys::Atomics::add_bigint(progress, thread_id, attempts.into()).map(drop)
+                }
+                None => match meter.record(attempts, now_ms()) {
+                    Some(report) => outbox.post(&report),
+                    None => Ok(()),
+                },
+            };
+            stopped = reported.and_then(|()| shared.is_cancelled());
+            match stopped {
+                Ok(false) => ControlFlow::Continue(()),
+                _ => ControlFlow::Break(()),
+            }
+        });
+
+        if let Some((nonce, hash)) = solution {
+            return Ok(WorkerMessage::solved(
+                difficulty,
+                nonce[1] as u64 | (nonce[0] as u64) << 32,
+                Hash(hash),
+            ));
+        }
+        if stopped? {
+            return Ok(WorkerMessage::Cancelled);
+        }
+    }
+    Ok(TaskError::Exhausted.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_verify() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        for difficulty in 1..=6 {
+            let (nonce, hash) = solve(data, difficulty).unwrap();
+            assert_eq!(verify(data, difficulty, nonce), Ok(hash));
+
+            // Mirror blake3Prf on the server side
+            let mut ref_hasher = ::blake3::Hasher::new();
+            ref_hasher.update(&challenge_salt(data));
+            ref_hasher.update(&nonce.rotate_left(32).to_le_bytes());
+            assert_eq!(hash.to_hex(), ref_hasher.finalize().to_hex().as_str());
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_verify_keyed() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        let key = [0x42; 32];
+        let context = "cerberus 2024-01-01 test context";
+
+        let (nonce, hash) = solve_keyed(&key, data, 8).unwrap();
+        assert_eq!(verify_keyed(&key, data, 8, nonce), Ok(hash));
+        let mut ref_hasher = ::blake3::Hasher::new_keyed(&key);
+        ref_hasher.update(&challenge_salt(data));
+        ref_hasher.update(&nonce.rotate_left(32).to_le_bytes());
+        assert_eq!(hash.to_hex(), ref_hasher.finalize().to_hex().as_str());
+
+        let (nonce, hash) = solve_derive_key(context, data, 8).unwrap();
+        assert_eq!(verify_derive_key(context, data, 8, nonce), Ok(hash));
+        let mut ref_hasher = ::blake3::Hasher::new_derive_key(context);
+        ref_hasher.update(&challenge_salt(data));
+        ref_hasher.update(&nonce.rotate_left(32).to_le_bytes());
+        assert_eq!(hash.to_hex(), ref_hasher.finalize().to_hex().as_str());
+
+        // a solution is bound to its mode and key
+        let (nonce, _) = solve_keyed(&key, data, 10).unwrap();
+        assert!(verify_keyed(&[0x43; 32], data, 10, nonce).is_err());
+        assert!(verify(data, 10, nonce).is_err());
+        assert_eq!(
+            verify_keyed(&key, data, 0, nonce),
+            Err(VerifyError::InvalidDifficulty(0))
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_claimed_batch() {
+        assert_eq!(claimed_batch(0u64), Some(0));
+        assert_eq!(claimed_batch(u64::from(u32::MAX)), Some(u32::MAX));
+        assert_eq!(claimed_batch(1u64 << 32), None);
+        assert_eq!(claimed_batch(1i64 << 32), None);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_verify_rejects() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        let (nonce, _) = solve(data, 8).unwrap();
+
+        assert_eq!(
+            verify(data, 0, nonce),
+            Err(VerifyError::InvalidDifficulty(0))
+        );
+        assert_eq!(
+            verify(data, 129, nonce),
+            Err(VerifyError::InvalidDifficulty(129))
+        );
+        assert_eq!(
+            verify(data, 256, nonce),
+            Err(VerifyError::InvalidDifficulty(256))
+        );
+
+        let mut rejected = 0;
+        for tampered in [nonce ^ 1, nonce ^ 1 << 32, nonce.wrapping_add(1 << 20)] {
+            if let Err(VerifyError::InsufficientWork(hash)) = verify(data, 8, tampered) {
+                assert!(!Target::from_difficulty(8).unwrap().is_met(&hash.0));
+                rejected += 1;
+            }
+        }
+        assert!(rejected > 0);
+        assert!(matches!(
+            verify("some other challenge", 8, nonce),
+            Err(VerifyError::InsufficientWork(_))
+        ));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_stepped_solver() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        let expected = solve(data, 6).unwrap();
+
+        let mut solver = SteppedSolver::new(data.to_string(), 6).unwrap();
+        let mut steps = 0;
+        let (nonce, hash) = loop {
+            match solver.advance(100) {
+                SolveStatus::Found(nonce, hash) => break (nonce, hash),
+                SolveStatus::BudgetSpent(_) => steps += 1,
+                SolveStatus::Exhausted => panic!("search exhausted"),
             }
         };
+        assert!(steps > 0);
+        assert_eq!((nonce, hash), expected);
+        assert_eq!(solver.advance(100), SolveStatus::Found(nonce, hash));
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_stepped_solver_checkpoint() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        let mut solver = SteppedSolver::new(data.to_string(), 6).unwrap();
+        let checkpoint = Checkpoint::new(1, 2);
+        solver
+            .set_checkpoint(serde_wasm_bindgen::to_value(&checkpoint).unwrap())
+            .unwrap();
+        assert_eq!(
+            serde_wasm_bindgen::from_value::<Checkpoint>(solver.checkpoint().unwrap()).unwrap(),
+            checkpoint
+        );
+
+        let stalled = Checkpoint {
+            stride: 0,
+            ..checkpoint
+        };
+        assert!(solver
+            .set_checkpoint(serde_wasm_bindgen::to_value(&stalled).unwrap())
+            .is_err());
+    }
 
-        let mut hash_hex = [0; 64];
-        encode_hex_le(&mut hash_hex, hash);
-        let resp = Resp {
-            hash: String::from_utf8(hash_hex.to_vec()).unwrap(),
+    /// Run [`process_task`] to completion, collecting the messages it posts
+    #[cfg(target_arch = "wasm32")]
+    fn run_process_task(
+        data: &str,
+        difficulty: u32,
+        thread_id: u32,
+        threads: u32,
+        cancel: Option<js_sys::Int32Array>,
+        progress: Option<js_sys::BigInt64Array>,
+        batches: Option<js_sys::BigInt64Array>,
+    ) -> Vec<WorkerMessage> {
+        let task = PageMessage::Task {
+            data: data.to_string(),
             difficulty,
-            nonce: nonce[1] as u64 | (nonce[0] as u64) << 32,
+            thread_id,
+            threads,
         };
-        worker
-            .post_message(
-                &serde_wasm_bindgen::to_value(&resp).expect("Failed to serialize response"),
+        collect_messages(
+            serde_wasm_bindgen::to_value(&task).unwrap(),
+            cancel,
+            progress,
+            batches,
+        )
+    }
+
+    /// Run [`process_task`] on a task as the page sent it, collecting the messages it posts
+    #[cfg(target_arch = "wasm32")]
+    fn collect_messages(
+        task: JsValue,
+        cancel: Option<js_sys::Int32Array>,
+        progress: Option<js_sys::BigInt64Array>,
+        batches: Option<js_sys::BigInt64Array>,
+    ) -> Vec<WorkerMessage> {
+        use std::{cell::RefCell, rc::Rc};
+
+        let messages = Rc::new(RefCell::new(Vec::new()));
+        let post = Closure::<dyn FnMut(JsValue)>::new({
+            let messages = messages.clone();
+            move |message| {
+                let message = serde_wasm_bindgen::from_value(message).unwrap();
+                messages.borrow_mut().push(message)
+            }
+        });
+        let options = js_sys::Object::new();
+        for (key, value) in [
+            ("cancel", cancel.map(JsValue::from)),
+            ("post", Some(post.as_ref().clone())),
+            ("progress", progress.map(JsValue::from)),
+            ("batches", batches.map(JsValue::from)),
+        ] {
+            if let Some(value) = value {
+                js_sys::Reflect::set(&options, &key.into(), &value).unwrap();
+            }
+        }
+        process_task(task, Some(options.unchecked_into())).unwrap();
+
+        let messages = messages.take();
+        assert_eq!(messages[0], WorkerMessage::hello(Backend::detect()));
+        messages[1..].to_vec()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_process_task_callback() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        let messages = run_process_task(data, 6, 0, 1, None, None, None);
+
+        let (result, progress) = messages.split_last().unwrap();
+        assert!(progress
+            .iter()
+            .all(|message| matches!(message, WorkerMessage::Progress { .. })));
+        let WorkerMessage::Solved { hash, nonce, .. } = result else {
+            panic!("expected a solution, got {result:?}");
+        };
+        assert_eq!(*hash, verify(data, 6, *nonce).unwrap().to_hex());
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_process_task_cancel() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        let cancel = js_sys::Int32Array::new(&js_sys::SharedArrayBuffer::new(4));
+        cancel.set_index(0, 1);
+
+        // far too hard to be solved before the flag is first checked
+        let messages = run_process_task(data, 16, 0, 1, Some(cancel), None, None);
+        let (result, progress) = messages.split_last().unwrap();
+        assert!(progress
+            .iter()
+            .all(|message| matches!(message, WorkerMessage::Progress { .. })));
+        assert_eq!(*result, WorkerMessage::Cancelled);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_process_task_progress_counters() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        let buffer = js_sys::SharedArrayBuffer::new(8 * 2);
+        let progress = js_sys::BigInt64Array::new(&buffer);
+        let messages = run_process_task(data, 8, 1, 2, None, Some(progress.clone()), None);
+
+        let [WorkerMessage::Solved { hash, nonce, .. }] = &messages[..] else {
+            panic!("expected only a solution, got {messages:?}");
+        };
+        assert_eq!(*hash, verify(data, 8, *nonce).unwrap().to_hex());
+        assert_eq!(progress.get_index(0), 0);
+        assert!(progress.get_index(1) > 0);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_process_task_shared_batches() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        let batches = js_sys::BigInt64Array::new(&js_sys::SharedArrayBuffer::new(8));
+
+        // the thread ID no longer picks the batch, the cursor does
+        batches.set_index(0, 5);
+        let messages = run_process_task(data, 6, 0, 2, None, None, Some(batches.clone()));
+        let [WorkerMessage::Solved { hash, nonce, .. }] = &messages[..] else {
+            panic!("expected only a solution, got {messages:?}");
+        };
+        assert_eq!(nonce >> 32, 5);
+        assert_eq!(*hash, verify(data, 6, *nonce).unwrap().to_hex());
+        assert_eq!(batches.get_index(0), 6);
+
+        batches.set_index(0, 1 << 32);
+        assert_eq!(
+            run_process_task(data, 6, 0, 2, None, None, Some(batches)),
+            [TaskError::Exhausted.into()]
+        );
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_process_task_errors() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        for (difficulty, thread_id, threads, error) in [
+            (0, 0, 1, TaskError::InvalidDifficulty(0)),
+            (256, 0, 1, TaskError::InvalidDifficulty(256)),
+            (6, 0, 0, TaskError::NoThreads),
+            (
+                6,
+                2,
+                2,
+                TaskError::InvalidThreadId {
+                    thread_id: 2,
+                    threads: 2,
+                },
+            ),
+        ] {
+            assert_eq!(
+                run_process_task(data, difficulty, thread_id, threads, None, None, None),
+                [error.into()]
+            );
+        }
+
+        for task in [
+            JsValue::NULL,
+            js_sys::JSON::parse(
+                r#"{"type":"task","data":"","difficulty":-1,"threadId":0,"threads":1}"#,
             )
-            .expect("Failed to send message");
-        return;
+            .unwrap(),
+        ] {
+            assert_eq!(
+                collect_messages(task, None, None, None),
+                [TaskError::InvalidTask.into()]
+            );
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_message_prefix_length() {
+        for (len, error) in [
+            (0, PrefixError::Empty),
+            (1, PrefixError::Unaligned(1)),
+            (63, PrefixError::Unaligned(63)),
+            (65, PrefixError::Unaligned(65)),
+            (960 + 64, PrefixError::TooLong(1024)),
+            (2048, PrefixError::TooLong(2048)),
+        ] {
+            assert_eq!(
+                CerberusMessage::new(&vec![b'a'; len], 0).err(),
+                Some(error),
+                "len {len}"
+            );
+        }
+        for len in [64, 128, 960] {
+            let prefix = vec![b'a'; len];
+            let message = CerberusMessage::new(&prefix, 7).unwrap();
+
+            let mut ref_hasher = ::blake3::Hasher::new();
+            ref_hasher.update(&prefix);
+            ref_hasher.update(&(7u64 | 42 << 32).to_le_bytes());
+            assert_eq!(
+                Hash(message.hash(42)).to_hex(),
+                ref_hasher.finalize().to_hex().as_str(),
+                "len {len}"
+            );
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_message_modes() {
+        let prefix = challenge_salt("Accept-Language=en,X-Real-IP=127.0.0.1");
+        let target = Target::from_dubits(6).unwrap();
+        let key = b"per deployment secret, 32 bytes!";
+        let context = "cerberus.example.org 2025-01-01 puzzle";
+
+        let keyed = CerberusMessage::new_keyed(key, &prefix, 3).unwrap();
+        let derived = CerberusMessage::new_derive_key(context, &prefix, 3).unwrap();
+        let mut ref_keyed = ::blake3::Hasher::new_keyed(key);
+        let mut ref_derived = ::blake3::Hasher::new_derive_key(context);
+        let mut ref_plain = ::blake3::Hasher::new();
+        for hasher in [&mut ref_keyed, &mut ref_derived, &mut ref_plain] {
+            hasher.update(&prefix);
+        }
+
+        for (message, mut ref_hasher) in [(keyed, ref_keyed), (derived, ref_derived)] {
+            let (nonce, hash) = CerberusSolver::from(message.clone())
+                .solve(&target, |_| ControlFlow::Continue(()))
+                .unwrap();
+            assert_eq!(nonce[0], 3);
+            assert_eq!(hash, message.hash(nonce[1]));
+
+            let final_nonce = (nonce[0] as u64 | (nonce[1] as u64) << 32).to_le_bytes();
+            ref_hasher.update(&final_nonce);
+            assert_eq!(Hash(hash).to_hex(), ref_hasher.finalize().to_hex().as_str());
+
+            let mut output = [0u8; 200];
+            message.xof(nonce[1]).fill(&mut output);
+            let mut expected = [0u8; 200];
+            ref_hasher.finalize_xof().fill(&mut expected);
+            assert_eq!(output, expected);
+
+            // the same nonce is not a solution to the same prefix in any other mode
+            let mut ref_plain = ref_plain.clone();
+            ref_plain.update(&final_nonce);
+            assert_ne!(Hash(hash).to_hex(), ref_plain.finalize().to_hex().as_str());
+        }
     }
 }
@@ -0,0 +1,177 @@
+//! Multi-threaded native solver for clients outside the browser
+use core::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::solver::Solver;
+use crate::{challenge_salt, Backend, CerberusMessage, Hash, Target};
+
+/// A solution found by [`solve_parallel`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Solution {
+    /// The nonce, in the format reported by [`crate::process_task`]
+    pub nonce: u64,
+    /// The hash of the solution
+    pub hash: Hash,
+    /// The number of attempts made by all threads.
+    ///
+    /// Threads other than the one that found the solution only count attempts they reported, so
+    /// this is exact only up to the solver's report period.
+    pub attempts: u64,
+    /// The backend that found the solution
+    pub backend: Backend,
+}
+
+/// Solve a Cerberus challenge on multiple threads.
+///
+/// Threads claim batches in order from a shared cursor, taking the next unclaimed one whenever
+/// they finish one, so slower threads search less instead of holding up their share. All threads
+/// stop at the first solution. A thread count of 0 uses the available parallelism.
+///
+/// Returns None when the difficulty is invalid or the key space is exhausted.
+pub fn solve_parallel(data: &str, difficulty: u32, threads: u32) -> Option<Solution> {
+    solve_parallel_target(data, &Target::from_difficulty(difficulty)?, threads)
+}
+
+/// Solve a Cerberus challenge with an arbitrary target on multiple threads.
+///
+/// See [`solve_parallel`]. Uses the backend from [`Backend::detect`].
+pub fn solve_parallel_target(data: &str, target: &Target, threads: u32) -> Option<Solution> {
+    solve_parallel_backend(data, target, threads, Backend::detect())
+}
+
+/// Solve a Cerberus challenge with an arbitrary target on multiple threads, using a specific
+/// backend.
+///
+/// See [`solve_parallel`].
+///
+/// # Panics
+///
+/// Panics if the backend is not supported on this machine.
+pub fn solve_parallel_backend(
+    data: &str,
+    target: &Target,
+    threads: u32,
+    backend: Backend,
+) -> Option<Solution> {
+    assert!(
+        backend.is_supported(),
+        "backend {backend} is not supported on this machine"
+    );
+    let (nonce, hash, attempts) =
+        with_backend!(backend, S => solve_parallel_with::<S>(data, target, threads))??;
+    Some(Solution {
+        nonce,
+        hash,
+        attempts,
+        backend,
+    })
+}
+
+fn solve_parallel_with<S: Solver + From<CerberusMessage>>(
+    data: &str,
+    target: &Target,
+    threads: u32,
+) -> Option<(u64, Hash, u64)> {
+    let threads = match threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get() as u32),
+        threads => threads,
+    };
+    let salt = challenge_salt(data);
+
+    let cursor = AtomicU64::new(0);
+    let found = AtomicBool::new(false);
+    let attempts = AtomicU64::new(0);
+    let solution = Mutex::new(None);
+
+    std::thread::scope(|s| {
+        for thread_id in 0..threads {
+            let (salt, cursor, found, attempts, solution) =
+                (&salt, &cursor, &found, &attempts, &solution);
+            s.spawn(move || {
+                while let Some(set) = crate::claimed_batch(cursor.fetch_add(1, Ordering::Relaxed)) {
+                    let Ok(message) = CerberusMessage::new(salt, set) else {
+                        return;
+                    };
+                    let mut solver = S::from(message);
+                    solver.set_report_slot(thread_id, threads);
+
+                    let mut reported = 0u64;
+                    let result = solver.solve(target, |n| {
+                        reported += u64::from(n);
+                        if found.load(Ordering::Relaxed) {
+                            ControlFlow::Break(())
+                        } else {
+                            ControlFlow::Continue(())
+                        }
+                    });
+
+                    let Some((nonce, hash)) = result else {
+                        attempts.fetch_add(reported, Ordering::Relaxed);
+                        if found.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        continue;
+                    };
+
+                    attempts.fetch_add(u64::from(nonce[1]) + 1, Ordering::Relaxed);
+                    if !found.swap(true, Ordering::Relaxed) {
+                        *solution.lock().unwrap() =
+                            Some((nonce[1] as u64 | (nonce[0] as u64) << 32, Hash(hash)));
+                    }
+                    return;
+                }
+            });
+        }
+    });
+
+    let (nonce, hash) = solution.into_inner().unwrap()?;
+    Some((nonce, hash, attempts.into_inner()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_parallel() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        for threads in [1, 3, 8] {
+            let solution = solve_parallel(data, 7, threads).unwrap();
+            assert_eq!(crate::verify(data, 7, solution.nonce), Ok(solution.hash));
+            assert!(solution.nonce >> 32 < u64::from(threads));
+            assert!(solution.attempts > solution.nonce & 0xffff_ffff);
+        }
+
+        let solution = solve_parallel(data, 5, 0).unwrap();
+        assert_eq!(crate::verify(data, 5, solution.nonce), Ok(solution.hash));
+
+        assert_eq!(solve_parallel(data, 0, 4), None);
+    }
+
+    #[test]
+    fn test_solve_parallel_target() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        let target = Target::from_expected_attempts(100_000);
+        let solution = solve_parallel_target(data, &target, 4).unwrap();
+        assert_eq!(
+            crate::verify_target(data, &target, solution.nonce),
+            Ok(solution.hash)
+        );
+    }
+
+    #[test]
+    fn test_solve_parallel_backends() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        let target = Target::from_dubits(6).unwrap();
+        for backend in Backend::supported() {
+            let solution = solve_parallel_backend(data, &target, 3, backend).unwrap();
+            assert_eq!(solution.backend, backend);
+            assert_eq!(crate::verify(data, 6, solution.nonce), Ok(solution.hash));
+        }
+        assert_eq!(
+            solve_parallel(data, 5, 2).unwrap().backend,
+            Backend::detect()
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Turning the attempt counts reported by solvers into progress on a wall-clock interval
+
+use crate::{Target, WorkerMessage};
+
+/// How often [`crate::process_task`] reports progress, in milliseconds, also in `protocol.json`
+pub const PROGRESS_INTERVAL_MS: f64 = 200.0;
+
+/// Milliseconds since the Unix epoch
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+/// Milliseconds since the Unix epoch
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now_ms() -> f64 {
+    std::time::SystemTime::UNIX_EPOCH
+        .elapsed()
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64() * 1000.0)
+}
+
+/// Measures the progress of one of `threads` workers searching for a target
+#[derive(Debug, Clone)]
+pub(crate) struct ProgressMeter {
+    expected_attempts: f64,
+    threads: u32,
+    started: f64,
+    next_report: f64,
+    attempts: u64,
+}
+
+impl ProgressMeter {
+    pub fn new(target: &Target, threads: u32, now: f64) -> Self {
+        Self {
+            expected_attempts: target.expected_attempts(),
+            threads,
+            started: now,
+            next_report: now + PROGRESS_INTERVAL_MS,
+            attempts: 0,
+        }
+    }
+
+    /// Count more attempts, returning a report if one is due.
+    pub fn record(&mut self, attempts: u32, now: f64) -> Option<WorkerMessage> {
+        self.attempts += u64::from(attempts);
+        if now < self.next_report {
+            return None;
+        }
+        self.next_report = now + PROGRESS_INTERVAL_MS;
+        Some(self.report(now))
+    }
+
+    /// The progress so far.
+    ///
+    /// The ETA assumes every worker hashes at the rate of this one. Attempts are independent, so
+    /// it is the expected time from now and does not shrink as attempts accumulate.
+    pub fn report(&self, now: f64) -> WorkerMessage {
+        let elapsed = (now - self.started) / 1000.0;
+        let hashrate = if elapsed > 0.0 {
+            self.attempts as f64 / elapsed
+        } else {
+            0.0
+        };
+        let eta =
+            (hashrate > 0.0).then(|| self.expected_attempts / (hashrate * f64::from(self.threads)));
+        WorkerMessage::Progress {
+            attempts: self.attempts,
+            hashrate,
+            eta,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_progress_meter() {
+        let target = Target::from_expected_attempts(1 << 20);
+        let mut meter = ProgressMeter::new(&target, 4, 1000.0);
+        assert_eq!(
+            meter.report(1000.0),
+            WorkerMessage::Progress {
+                attempts: 0,
+                hashrate: 0.0,
+                eta: None,
+            }
+        );
+
+        assert_eq!(meter.record(16384, 1100.0), None);
+        assert_eq!(
+            meter.record(16384, 1256.0),
+            Some(WorkerMessage::Progress {
+                attempts: 32768,
+                hashrate: 128000.0,
+                eta: Some(2.048),
+            })
+        );
+        // the next report is due one interval after the last one, not after the start
+        assert_eq!(meter.record(16384, 1400.0), None);
+        assert!(meter.record(16384, 1456.0).is_some());
+    }
+}
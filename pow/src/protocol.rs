@@ -0,0 +1,161 @@
+//! Messages exchanged between the page and the solver workers
+//!
+//! Every message is an object tagged by its `type`, so fields can be added without breaking the
+//! other side. TypeScript definitions are emitted into the bindings generated by wasm-pack.
+//!
+//! The constants the page needs without loading the module are in `protocol.json`, which is
+//! copied into the package next to the bindings.
+
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+/// The version of the protocol, announced by workers in [`WorkerMessage::Hello`]
+///
+/// Only incompatible changes bump it, new fields and messages are not. Also in `protocol.json`.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// A message from the page to a worker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PageMessage {
+    /// Solve a challenge as thread `thread_id` of `threads`, see [`crate::process_task`]
+    Task {
+        data: String,
+        difficulty: u32,
+        #[serde(rename = "threadId")]
+        thread_id: u32,
+        threads: u32,
+    },
+}
+
+/// A message from a worker to the page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Tsify)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum WorkerMessage {
+    /// Sent before anything else for every task
+    Hello {
+        /// See [`PROTOCOL_VERSION`]
+        version: u32,
+        /// The name of the solver backend, see [`crate::Backend`]
+        backend: String,
+    },
+    /// Sent on a wall-clock interval while solving
+    Progress {
+        /// The number of nonces this worker has tried so far
+        attempts: u64,
+        /// The nonces this worker tries per second
+        hashrate: f64,
+        /// The expected seconds until a solution, once the hashrate is known
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[tsify(optional)]
+        eta: Option<f64>,
+    },
+    /// The challenge is solved, nothing follows
+    Solved {
+        /// The hex encoded hash
+        hash: String,
+        /// The difficulty the challenge was solved at
+        difficulty: u32,
+        /// The nonce, with the batch ID in the upper 32 bits
+        nonce: u64,
+    },
+    /// The task failed, nothing follows
+    Error {
+        /// The kind of error, see [`crate::TaskError::code`]
+        code: String,
+        /// A description of the error for humans
+        message: String,
+    },
+    /// The task stopped after the page asked it to, nothing follows
+    Cancelled,
+}
+
+impl WorkerMessage {
+    /// The greeting of a worker solving with the given backend
+    pub fn hello(backend: crate::Backend) -> Self {
+        Self::Hello {
+            version: PROTOCOL_VERSION,
+            backend: backend.name().to_string(),
+        }
+    }
+
+    /// A solution of a challenge at `difficulty`
+    pub fn solved(difficulty: u32, nonce: u64, hash: crate::Hash) -> Self {
+        Self::Solved {
+            hash: hash.to_hex(),
+            difficulty,
+            nonce,
+        }
+    }
+}
+
+impl From<crate::TaskError> for WorkerMessage {
+    fn from(error: crate::TaskError) -> Self {
+        Self::Error {
+            code: error.code().to_string(),
+            message: error.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_protocol_json() {
+        let constants: serde_json::Value =
+            serde_json::from_str(include_str!("../protocol.json")).unwrap();
+        assert_eq!(constants["version"], PROTOCOL_VERSION);
+        assert_eq!(
+            constants["progressIntervalMs"].as_f64(),
+            Some(crate::PROGRESS_INTERVAL_MS)
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_message_tags() {
+        assert_eq!(
+            serde_json::to_value(WorkerMessage::Progress {
+                attempts: 4096,
+                hashrate: 1024.0,
+                eta: Some(0.5),
+            })
+            .unwrap(),
+            serde_json::json!({ "type": "progress", "attempts": 4096, "hashrate": 1024.0, "eta": 0.5 })
+        );
+        assert_eq!(
+            serde_json::to_value(WorkerMessage::Cancelled).unwrap(),
+            serde_json::json!({ "type": "cancelled" })
+        );
+        assert_eq!(
+            serde_json::to_value(WorkerMessage::from(crate::TaskError::NoThreads)).unwrap(),
+            serde_json::json!({
+                "type": "error",
+                "code": "no-threads",
+                "message": "the number of threads must not be zero",
+            })
+        );
+
+        let task: PageMessage = serde_json::from_value(serde_json::json!({
+            "type": "task",
+            "data": "challenge",
+            "difficulty": 4,
+            "threadId": 1,
+            "threads": 2,
+            "addedLater": true,
+        }))
+        .unwrap();
+        assert_eq!(
+            task,
+            PageMessage::Task {
+                data: "challenge".to_string(),
+                difficulty: 4,
+                thread_id: 1,
+                threads: 2,
+            }
+        );
+    }
+}
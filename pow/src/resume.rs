@@ -0,0 +1,176 @@
+//! Solving in bounded slices of work that can be resumed later, possibly somewhere else
+
+use core::ops::ControlFlow;
+use serde::{Deserialize, Serialize};
+
+use crate::solver::Solver;
+use crate::{challenge_salt, CerberusMessage, Hash, Target};
+
+/// Where a search stopped, to continue it with [`solve_budget`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The batch ID being searched
+    pub batch_id: u32,
+    /// The next nonce to try in the batch
+    pub nonce: u32,
+    /// How far to advance the batch ID once the batch is exhausted
+    pub stride: u32,
+}
+
+impl Checkpoint {
+    /// The start of the search for one of `threads` workers, which take batch IDs in turn like
+    /// [`crate::process_task`] does without a shared cursor.
+    pub const fn new(thread_id: u32, threads: u32) -> Self {
+        Self {
+            batch_id: thread_id,
+            nonce: 0,
+            stride: threads,
+        }
+    }
+}
+
+impl Default for Checkpoint {
+    fn default() -> Self {
+        Self::new(0, 1)
+    }
+}
+
+/// The outcome of [`solve_budget`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveStatus {
+    /// A solution, with the nonce in the format reported by [`crate::process_task`]
+    Found(u64, Hash),
+    /// Every batch ID of the search has been tried
+    Exhausted,
+    /// The budget ran out before a solution was found, continue from the checkpoint
+    BudgetSpent(Checkpoint),
+}
+
+/// Solve a Cerberus challenge from a checkpoint, trying at most about `budget` nonces.
+///
+/// Multi-lane solvers round the slice out to whole iterations, so consecutive slices may overlap
+/// by a few nonces but never leave any out.
+///
+/// Uses the backend from [`crate::Backend::detect`].
+pub fn solve_budget(data: &str, target: &Target, from: Checkpoint, budget: u64) -> SolveStatus {
+    Search::new(data).solve_budget(target, from, budget)
+}
+
+/// A challenge prepared once for [`solve_budget`] slices
+#[derive(Debug, Clone)]
+pub(crate) struct Search {
+    message: CerberusMessage,
+}
+
+impl Search {
+    pub fn new(data: &str) -> Self {
+        let message =
+            CerberusMessage::new(&challenge_salt(data), 0).expect("salt is always a valid prefix");
+        Self { message }
+    }
+
+    /// See [`solve_budget`]
+    pub fn solve_budget(&self, target: &Target, from: Checkpoint, budget: u64) -> SolveStatus {
+        with_detected_backend!(S => self.solve_budget_with::<S>(target, from, budget))
+    }
+
+    fn solve_budget_with<S: Solver + From<CerberusMessage>>(
+        &self,
+        target: &Target,
+        from: Checkpoint,
+        budget: u64,
+    ) -> SolveStatus {
+        let mut checkpoint = from;
+        let mut budget = budget;
+
+        while budget > 0 {
+            // the midstate only covers the salt, so batches differ in the batch ID alone
+            let message = CerberusMessage {
+                batch_id: checkpoint.batch_id,
+                ..self.message.clone()
+            };
+            let end = u32::try_from(u64::from(checkpoint.nonce) + budget).unwrap_or(u32::MAX);
+
+            let mut solver = S::from(message);
+            solver.set_nonce_range(checkpoint.nonce..end);
+            if let Some((nonce, hash)) = solver.solve(target, |_| ControlFlow::Continue(())) {
+                return SolveStatus::Found(nonce[1] as u64 | (nonce[0] as u64) << 32, Hash(hash));
+            }
+
+            budget -= u64::from(end - checkpoint.nonce);
+            if end < u32::MAX {
+                checkpoint.nonce = end;
+            } else {
+                let Some(batch_id) = checkpoint.batch_id.checked_add(checkpoint.stride) else {
+                    return SolveStatus::Exhausted;
+                };
+                checkpoint.batch_id = batch_id;
+                checkpoint.nonce = 0;
+            }
+        }
+
+        SolveStatus::BudgetSpent(checkpoint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_solve_budget_resumes() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        let target = Target::from_dubits(6).unwrap();
+        let expected = crate::solve_target(data, &target).unwrap();
+
+        let mut checkpoint = Checkpoint::default();
+        let mut slices = 0;
+        let (nonce, hash) = loop {
+            match solve_budget(data, &target, checkpoint, 100) {
+                SolveStatus::Found(nonce, hash) => break (nonce, hash),
+                SolveStatus::BudgetSpent(next) => checkpoint = next,
+                SolveStatus::Exhausted => panic!("search exhausted"),
+            }
+            slices += 1;
+        };
+
+        assert!(slices > 0, "the budget should run out at least once");
+        assert_eq!((nonce, hash), expected);
+        assert_eq!(crate::verify_target(data, &target, nonce), Ok(hash));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_solve_budget_checkpoint() {
+        let data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+        let target = Target::from_dubits(16).unwrap();
+        let from = Checkpoint::new(1, 4);
+
+        assert_eq!(
+            solve_budget(data, &target, from, 0),
+            SolveStatus::BudgetSpent(from)
+        );
+
+        let end = Checkpoint {
+            batch_id: 5,
+            nonce: 0,
+            stride: 4,
+        };
+        let last = Checkpoint {
+            nonce: u32::MAX - 100,
+            ..from
+        };
+        assert_eq!(
+            solve_budget(data, &target, last, 100),
+            SolveStatus::BudgetSpent(end)
+        );
+        assert_eq!(
+            solve_budget(data, &target, from, 1000),
+            SolveStatus::BudgetSpent(Checkpoint {
+                nonce: 1000,
+                ..from
+            })
+        );
+    }
+}
@@ -4,21 +4,60 @@ pub mod scalar;
 #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
 pub mod simd128;
 
+#[cfg(target_arch = "aarch64")]
+pub mod neon;
+
+#[cfg(target_arch = "x86_64")]
+pub mod sse41;
+
+#[cfg(target_arch = "x86_64")]
+pub mod avx2;
+
+#[cfg(target_arch = "x86_64")]
+pub mod avx512;
+
+pub(crate) mod lanes;
+
+use core::ops::{ControlFlow, Range};
+
 /// A generic solver trait
 pub trait Solver {
     /// Perform precomputation and set the time slot for reporting progress.
     fn set_report_slot(&mut self, tid: u32, threads: u32);
 
+    /// Restrict the search to a range of nonces, `0..u32::MAX` by default.
+    ///
+    /// Multi-lane solvers may search slightly beyond either end to keep their lanes aligned.
+    fn set_nonce_range(&mut self, nonces: Range<u32>);
+
     /// Returns a valid nonce and its corresponding hash value.
     ///
     /// Returns None when the solver cannot solve the prefix.
     ///
     /// Progress report callback is periodically called with the number of _additional_ attempts made
-    /// since the last report.
+    /// since the last report. The solver gives up when it returns [`ControlFlow::Break`].
     ///
     /// Failure is usually because the key space is exhausted (or presumed exhausted).
     /// It should by design happen extremely rarely for common difficulty settings.
-    fn solve<P: FnMut(u32)>(&mut self, mask: u32, progress: P) -> Option<([u32; 2], [u32; 8])>;
+    fn solve<P: FnMut(u32) -> ControlFlow<()>>(
+        &mut self,
+        target: &crate::Target,
+        progress: P,
+    ) -> Option<([u32; 2], [u32; 8])>;
+}
+
+/// The iterations a solver with `lanes` lanes needs to cover `nonces`, iteration `i` hashing
+/// nonces `i * lanes..(i + 1) * lanes`
+pub(crate) fn lane_reps(nonces: &Range<u32>, lanes: u32) -> Range<u32> {
+    nonces.start / lanes..nonces.end.div_ceil(lanes)
+}
+
+/// The iteration within each `report_period` at which thread `tid` of `threads` reports, spreading
+/// the reports of all threads evenly over the period
+pub(crate) fn report_slot(tid: u32, threads: u32, report_period: u32) -> u32 {
+    // in 64 bits, as the product overflows 32 bits with many threads
+    let slot = u64::from(tid) * u64::from(report_period) / u64::from(threads);
+    u32::try_from(slot).unwrap_or(u32::MAX)
 }
 
 #[cfg(test)]
@@ -47,24 +86,29 @@ pub(crate) mod tests {
         }
     }
 
-    pub(crate) fn test_cerberus_validator<
-        S: Solver,
-        F: for<'a> FnMut(&'a [u8; 64]) -> Option<S>,
-    >(
+    pub(crate) fn test_cerberus_validator<S: Solver, F: for<'a> FnMut(&'a [u8]) -> Option<S>>(
         mut factory: F,
     ) {
-        for df in 6..=8 {
-            let mask = crate::compute_mask_cerberus(df.try_into().unwrap());
+        let targets = (6..=8)
+            .map(|df| (df, crate::Target::from_dubits(df).unwrap()))
+            .chain([(6, crate::Target::from_expected_attempts(5 << 12))]);
+        let seeds = [64, 320].map(|len| {
+            (0..len)
+                .map(|i| b'a'.wrapping_add(i as u8))
+                .collect::<Vec<_>>()
+        });
+        for ((df, target), test_seed) in targets.flat_map(|t| seeds.iter().map(move |s| (t, s))) {
+            let mask = target.prefilter_mask();
 
-            let test_seed: [u8; 64] = core::array::from_fn(|i| b'a'.wrapping_add(i as u8));
-
-            let Some(mut solver) = factory(&test_seed) else {
+            let Some(mut solver) = factory(test_seed) else {
                 panic!("solver is None for seed");
             };
 
-            let (nonce, hash) = solver.solve(mask, |_| {}).unwrap();
+            let (nonce, hash) = solver
+                .solve(&target, |_| ControlFlow::Continue(()))
+                .unwrap();
             let mut ref_hasher = ::blake3::Hasher::new();
-            ref_hasher.update(&test_seed);
+            ref_hasher.update(test_seed);
             let final_nonce = (nonce[0] as u64 | (nonce[1] as u64) << 32).to_le_bytes();
             ref_hasher.update(&final_nonce);
             let ref_hash = ref_hasher.finalize();
@@ -80,10 +124,35 @@ pub(crate) mod tests {
             let hit = (ref_hash[0] & mask) == 0;
             assert_eq!(hash, ref_hash, "incorrect output: {:?}", nonce);
             assert!(hit);
+            assert!(target.is_met(&ref_hash));
             assert!(legacy_check_dubit::check_leading_zero_dubits(df as usize)(
-                &ref_hash_bytes,
+                ref_hash_bytes,
                 df as usize
             ));
+
+            // a range holding just the first solution finds it again, one past it a later one
+            let mut solver = factory(test_seed).unwrap();
+            solver.set_nonce_range(nonce[1]..nonce[1] + 1);
+            assert_eq!(
+                solver.solve(&target, |_| ControlFlow::Continue(())),
+                Some((nonce, hash))
+            );
+            let mut solver = factory(test_seed).unwrap();
+            solver.set_nonce_range(nonce[1] + 16..u32::MAX);
+            let (later, _) = solver
+                .solve(&target, |_| ControlFlow::Continue(()))
+                .unwrap();
+            assert!(later[1] > nonce[1], "solution {later:?} before the range");
         }
     }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_report_slot() {
+        assert_eq!(report_slot(0, 1, 1024), 0);
+        assert_eq!(report_slot(3, 4, 1024), 768);
+        // the product alone would overflow 32 bits
+        assert_eq!(report_slot(1 << 20, 1 << 21, 16384), 8192);
+        assert_eq!(report_slot(u32::MAX - 1, u32::MAX, 16384), 16383);
+    }
 }
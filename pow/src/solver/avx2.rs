@@ -0,0 +1,112 @@
+use super::lanes::{solve_lanes, Lanes};
+use crate::CerberusMessage;
+use core::arch::x86_64::*;
+use core::ops::{ControlFlow, Range};
+
+impl Lanes for __m256i {
+    const LANES: u32 = 8;
+
+    #[inline(always)]
+    unsafe fn nonces(base: u32) -> Self {
+        unsafe {
+            _mm256_add_epi32(
+                _mm256_setr_epi32(0, 1, 2, 3, 4, 5, 6, 7),
+                _mm256_set1_epi32(base as _),
+            )
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn hits(word0: Self, mask: Self) -> u64 {
+        unsafe {
+            let zero = _mm256_cmpeq_epi32(_mm256_and_si256(word0, mask), _mm256_setzero_si256());
+            _mm256_movemask_ps(_mm256_castsi256_ps(zero)) as u64
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn store(self, lanes: &mut [u32; 64]) {
+        unsafe { _mm256_storeu_si256(lanes.as_mut_ptr().cast(), self) }
+    }
+}
+
+/// AVX2 Ceberus solver.
+pub struct CerberusSolver {
+    message: CerberusMessage,
+    report_slot: u32,
+    nonces: Range<u32>,
+}
+
+impl CerberusSolver {
+    const REPORT_PERIOD: u32 = 4096;
+
+    /// Whether the CPU supports this solver
+    pub fn is_supported() -> bool {
+        std::is_x86_feature_detected!("avx2")
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn solve_impl<P: FnMut(u32) -> ControlFlow<()>>(
+        &mut self,
+        target: &crate::Target,
+        progress: P,
+    ) -> Option<([u32; 2], [u32; 8])> {
+        // SAFETY: AVX2 is enabled on this function
+        unsafe {
+            solve_lanes::<__m256i, _>(
+                &self.message,
+                &self.nonces,
+                Self::REPORT_PERIOD,
+                self.report_slot,
+                target,
+                progress,
+            )
+        }
+    }
+}
+
+impl From<CerberusMessage> for CerberusSolver {
+    fn from(message: CerberusMessage) -> Self {
+        Self {
+            message,
+            report_slot: 0,
+            nonces: 0..u32::MAX,
+        }
+    }
+}
+
+impl crate::solver::Solver for CerberusSolver {
+    fn set_report_slot(&mut self, tid: u32, threads: u32) {
+        self.report_slot = super::report_slot(tid, threads, Self::REPORT_PERIOD);
+    }
+
+    fn set_nonce_range(&mut self, nonces: Range<u32>) {
+        self.nonces = nonces;
+    }
+
+    #[inline(never)]
+    fn solve<P: FnMut(u32) -> ControlFlow<()>>(
+        &mut self,
+        target: &crate::Target,
+        progress: P,
+    ) -> Option<([u32; 2], [u32; 8])> {
+        assert!(Self::is_supported(), "AVX2 is not supported on this CPU");
+        // SAFETY: the required target feature was detected above
+        unsafe { self.solve_impl(target, progress) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_cerberus() {
+        if !CerberusSolver::is_supported() {
+            return;
+        }
+        crate::solver::tests::test_cerberus_validator::<CerberusSolver, _>(|prefix| {
+            CerberusMessage::new(prefix, 0).ok().map(Into::into)
+        });
+    }
+}
@@ -0,0 +1,109 @@
+use super::lanes::{solve_lanes, Lanes};
+use crate::CerberusMessage;
+use core::arch::x86_64::*;
+use core::ops::{ControlFlow, Range};
+
+impl Lanes for __m512i {
+    const LANES: u32 = 16;
+
+    #[inline(always)]
+    unsafe fn nonces(base: u32) -> Self {
+        unsafe {
+            _mm512_add_epi32(
+                _mm512_setr_epi32(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15),
+                _mm512_set1_epi32(base as _),
+            )
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn hits(word0: Self, mask: Self) -> u64 {
+        unsafe { u64::from(_mm512_testn_epi32_mask(word0, mask)) }
+    }
+
+    #[inline(always)]
+    unsafe fn store(self, lanes: &mut [u32; 64]) {
+        unsafe { _mm512_storeu_si512(lanes.as_mut_ptr().cast(), self) }
+    }
+}
+
+/// AVX-512 Ceberus solver.
+pub struct CerberusSolver {
+    message: CerberusMessage,
+    report_slot: u32,
+    nonces: Range<u32>,
+}
+
+impl CerberusSolver {
+    const REPORT_PERIOD: u32 = 2048;
+
+    /// Whether the CPU supports this solver
+    pub fn is_supported() -> bool {
+        std::is_x86_feature_detected!("avx512f")
+    }
+
+    #[target_feature(enable = "avx512f")]
+    unsafe fn solve_impl<P: FnMut(u32) -> ControlFlow<()>>(
+        &mut self,
+        target: &crate::Target,
+        progress: P,
+    ) -> Option<([u32; 2], [u32; 8])> {
+        // SAFETY: AVX-512F is enabled on this function
+        unsafe {
+            solve_lanes::<__m512i, _>(
+                &self.message,
+                &self.nonces,
+                Self::REPORT_PERIOD,
+                self.report_slot,
+                target,
+                progress,
+            )
+        }
+    }
+}
+
+impl From<CerberusMessage> for CerberusSolver {
+    fn from(message: CerberusMessage) -> Self {
+        Self {
+            message,
+            report_slot: 0,
+            nonces: 0..u32::MAX,
+        }
+    }
+}
+
+impl crate::solver::Solver for CerberusSolver {
+    fn set_report_slot(&mut self, tid: u32, threads: u32) {
+        self.report_slot = super::report_slot(tid, threads, Self::REPORT_PERIOD);
+    }
+
+    fn set_nonce_range(&mut self, nonces: Range<u32>) {
+        self.nonces = nonces;
+    }
+
+    #[inline(never)]
+    fn solve<P: FnMut(u32) -> ControlFlow<()>>(
+        &mut self,
+        target: &crate::Target,
+        progress: P,
+    ) -> Option<([u32; 2], [u32; 8])> {
+        assert!(Self::is_supported(), "AVX-512 is not supported on this CPU");
+        // SAFETY: the required target feature was detected above
+        unsafe { self.solve_impl(target, progress) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_cerberus() {
+        if !CerberusSolver::is_supported() {
+            return;
+        }
+        crate::solver::tests::test_cerberus_validator::<CerberusSolver, _>(|prefix| {
+            CerberusMessage::new(prefix, 0).ok().map(Into::into)
+        });
+    }
+}
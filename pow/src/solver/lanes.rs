@@ -0,0 +1,87 @@
+//! The solve loop shared by all solvers
+//!
+//! Each backend implements [`Lanes`] for its register type and runs [`solve_lanes`] with its
+//! target feature enabled, hashing one nonce per lane.
+use crate::blake3::vector::{compress_mb, single_patch, Word};
+use crate::blake3::{TrailingBlock, Vector};
+use crate::{CerberusMessage, Target};
+use core::ops::{ControlFlow, Range};
+
+/// A register holding one nonce per lane.
+///
+/// # Safety
+///
+/// The methods may only be called when the target feature of the implementation is enabled.
+pub(crate) trait Lanes: Vector {
+    /// The number of lanes, at most 64
+    const LANES: u32;
+
+    /// The nonces `base..base + LANES`, in lane order
+    unsafe fn nonces(base: u32) -> Self;
+
+    /// Bit `i` is set if lane `i` of `word0` has none of the bits of `mask` set.
+    unsafe fn hits(word0: Self, mask: Self) -> u64;
+
+    /// Store the lanes to the start of `lanes`, in lane order.
+    unsafe fn store(self, lanes: &mut [u32; 64]);
+}
+
+/// Search `nonces` for a hash of `message` meeting `target`, see [`crate::solver::Solver::solve`].
+///
+/// Progress is reported every `report_period` iterations, offset by `report_slot`.
+///
+/// # Safety
+///
+/// The target feature of `V` must be enabled.
+#[inline(always)]
+pub(crate) unsafe fn solve_lanes<V: Lanes, P: FnMut(u32) -> ControlFlow<()>>(
+    message: &CerberusMessage,
+    nonces: &Range<u32>,
+    report_period: u32,
+    report_slot: u32,
+    target: &Target,
+    mut progress: P,
+) -> Option<([u32; 2], [u32; 8])> {
+    unsafe {
+        let mut block_template = [0; 16];
+        block_template[0] = message.batch_id;
+
+        let state =
+            crate::blake3::setup_block(message.midstate, 0, 8, message.trailing_block_flags());
+        let trailing_block = TrailingBlock::<V>::new(&state, message.batch_id);
+
+        let reps = crate::solver::lane_reps(nonces, V::LANES);
+        let mut nonce = V::nonces(reps.start * V::LANES);
+        let step = V::splat(V::LANES);
+        let maskv = V::splat(target.prefilter_mask());
+        for rep in reps {
+            let hits = V::hits(trailing_block.word0(nonce), maskv);
+
+            if hits != 0 {
+                crate::unlikely();
+
+                // the prefilter only looks at the first word, confirm with the full hash
+                let mut v = state.map(|word| V::splat(word));
+                compress_mb::<V, Word<1>>(&mut v, &block_template, &single_patch::<V, 1>(nonce));
+                let mut words = [[0; 64]; 8];
+                for (word, v) in words.iter_mut().zip(v) {
+                    v.store(word);
+                }
+
+                for lane_idx in (0..V::LANES).filter(|lane_idx| hits & (1 << lane_idx) != 0) {
+                    let hash = words.map(|word| word[lane_idx as usize]);
+                    if target.is_met(&hash) {
+                        return Some(([message.batch_id, rep * V::LANES + lane_idx], hash));
+                    }
+                }
+            }
+            nonce = nonce.add(step);
+
+            if rep % report_period == report_slot && progress(report_period * V::LANES).is_break() {
+                return None;
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,102 @@
+use super::lanes::{solve_lanes, Lanes};
+use crate::CerberusMessage;
+use core::arch::aarch64::*;
+use core::ops::{ControlFlow, Range};
+
+impl Lanes for uint32x4_t {
+    const LANES: u32 = 4;
+
+    #[inline(always)]
+    unsafe fn nonces(base: u32) -> Self {
+        unsafe { vaddq_u32(vld1q_u32([0, 1, 2, 3].as_ptr()), vdupq_n_u32(base)) }
+    }
+
+    #[inline(always)]
+    unsafe fn hits(word0: Self, mask: Self) -> u64 {
+        unsafe {
+            let masked = vandq_u32(word0, mask);
+            if vminvq_u32(masked) != 0 {
+                return 0;
+            }
+            let bits = vandq_u32(vceqzq_u32(masked), vld1q_u32([1, 2, 4, 8].as_ptr()));
+            u64::from(vaddvq_u32(bits))
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn store(self, lanes: &mut [u32; 64]) {
+        unsafe { vst1q_u32(lanes.as_mut_ptr(), self) }
+    }
+}
+
+/// NEON Ceberus solver.
+pub struct CerberusSolver {
+    message: CerberusMessage,
+    report_slot: u32,
+    nonces: Range<u32>,
+}
+
+impl CerberusSolver {
+    const REPORT_PERIOD: u32 = 8192;
+
+    #[target_feature(enable = "neon")]
+    fn solve_impl<P: FnMut(u32) -> ControlFlow<()>>(
+        &mut self,
+        target: &crate::Target,
+        progress: P,
+    ) -> Option<([u32; 2], [u32; 8])> {
+        // SAFETY: NEON is enabled on this function
+        unsafe {
+            solve_lanes::<uint32x4_t, _>(
+                &self.message,
+                &self.nonces,
+                Self::REPORT_PERIOD,
+                self.report_slot,
+                target,
+                progress,
+            )
+        }
+    }
+}
+
+impl From<CerberusMessage> for CerberusSolver {
+    fn from(message: CerberusMessage) -> Self {
+        Self {
+            message,
+            report_slot: 0,
+            nonces: 0..u32::MAX,
+        }
+    }
+}
+
+impl crate::solver::Solver for CerberusSolver {
+    fn set_report_slot(&mut self, tid: u32, threads: u32) {
+        self.report_slot = super::report_slot(tid, threads, Self::REPORT_PERIOD);
+    }
+
+    fn set_nonce_range(&mut self, nonces: Range<u32>) {
+        self.nonces = nonces;
+    }
+
+    #[inline(never)]
+    fn solve<P: FnMut(u32) -> ControlFlow<()>>(
+        &mut self,
+        target: &crate::Target,
+        progress: P,
+    ) -> Option<([u32; 2], [u32; 8])> {
+        // SAFETY: NEON is part of the aarch64 baseline
+        unsafe { self.solve_impl(target, progress) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_cerberus() {
+        crate::solver::tests::test_cerberus_validator::<CerberusSolver, _>(|prefix| {
+            CerberusMessage::new(prefix, 0).ok().map(Into::into)
+        });
+    }
+}
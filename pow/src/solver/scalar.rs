@@ -1,56 +1,77 @@
+use super::lanes::{solve_lanes, Lanes};
 use crate::CerberusMessage;
+use core::ops::{ControlFlow, Range};
+
+impl<const WAYS: usize> Lanes for [u32; WAYS] {
+    const LANES: u32 = WAYS as u32;
+
+    #[inline(always)]
+    unsafe fn nonces(base: u32) -> Self {
+        core::array::from_fn(|way| base.wrapping_add(way as u32))
+    }
+
+    #[inline(always)]
+    unsafe fn hits(word0: Self, mask: Self) -> u64 {
+        (0..WAYS).fold(0, |hits, way| {
+            hits | u64::from(word0[way] & mask[way] == 0) << way
+        })
+    }
+
+    #[inline(always)]
+    unsafe fn store(self, lanes: &mut [u32; 64]) {
+        lanes[..WAYS].copy_from_slice(&self);
+    }
+}
 
 /// Scalar fallback solver.
-pub struct CerberusSolver {
+///
+/// Hashes `WAYS` nonces per iteration with interleaved states, so the dependency chains of the
+/// round function overlap even without SIMD.
+pub struct CerberusSolver<const WAYS: usize = 1> {
     message: CerberusMessage,
-    attempted_nonces: u32,
     report_slot: u32,
+    nonces: Range<u32>,
 }
 
-impl From<CerberusMessage> for CerberusSolver {
+impl<const WAYS: usize> From<CerberusMessage> for CerberusSolver<WAYS> {
     fn from(message: CerberusMessage) -> Self {
         Self {
             message,
-            attempted_nonces: 0,
             report_slot: 0,
+            nonces: 0..u32::MAX,
         }
     }
 }
 
-impl CerberusSolver {
-    pub const REPORT_PERIOD: u32 = 16384;
+impl<const WAYS: usize> CerberusSolver<WAYS> {
+    const REPORT_PERIOD: u32 = 16384 / <[u32; WAYS]>::LANES;
 }
 
-impl crate::solver::Solver for CerberusSolver {
+impl<const WAYS: usize> crate::solver::Solver for CerberusSolver<WAYS> {
     fn set_report_slot(&mut self, tid: u32, threads: u32) {
-        self.report_slot = tid * Self::REPORT_PERIOD / threads;
+        self.report_slot = super::report_slot(tid, threads, Self::REPORT_PERIOD);
     }
 
-    fn solve<P: FnMut(u32)>(&mut self, mask: u32, mut progress: P) -> Option<([u32; 2], [u32; 8])> {
-        let mut msg = [0; 16];
-        msg[0] = self.message.batch_id;
-        for nonce in 0..u32::MAX {
-            msg[1] = nonce;
-
-            let hash = crate::blake3::compress8(
-                &self.message.midstate,
-                &msg,
-                0,
-                8,
-                self.message.trailing_block_flags(),
-            );
-            self.attempted_nonces += 1;
-            if self.attempted_nonces % Self::REPORT_PERIOD == self.report_slot {
-                progress(Self::REPORT_PERIOD);
-            }
-            if hash[0] & mask as u32 == 0 {
-                crate::unlikely();
+    fn set_nonce_range(&mut self, nonces: Range<u32>) {
+        self.nonces = nonces;
+    }
 
-                return Some(([self.message.batch_id, nonce], hash));
-            }
+    fn solve<P: FnMut(u32) -> ControlFlow<()>>(
+        &mut self,
+        target: &crate::Target,
+        progress: P,
+    ) -> Option<([u32; 2], [u32; 8])> {
+        // SAFETY: the scalar vector operations have no requirements
+        unsafe {
+            solve_lanes::<[u32; WAYS], _>(
+                &self.message,
+                &self.nonces,
+                Self::REPORT_PERIOD,
+                self.report_slot,
+                target,
+                progress,
+            )
         }
-
-        None
     }
 }
 
@@ -62,7 +83,15 @@ mod tests {
     #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn test_solve_cerberus() {
         crate::solver::tests::test_cerberus_validator::<CerberusSolver, _>(|prefix| {
-            CerberusMessage::new(prefix, 0).map(Into::into)
+            CerberusMessage::new(prefix, 0).ok().map(Into::into)
+        });
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_solve_cerberus_interleaved() {
+        crate::solver::tests::test_cerberus_validator::<CerberusSolver<2>, _>(|prefix| {
+            CerberusMessage::new(prefix, 0).ok().map(Into::into)
         });
     }
 }
@@ -1,77 +1,96 @@
+use super::lanes::{solve_lanes, Lanes};
 use crate::CerberusMessage;
 use core::arch::wasm32::*;
+use core::ops::{ControlFlow, Range};
+
+impl<const WAYS: usize> Lanes for [v128; WAYS] {
+    const LANES: u32 = 4 * WAYS as u32;
+
+    #[inline(always)]
+    unsafe fn nonces(base: u32) -> Self {
+        core::array::from_fn(|way| {
+            let base = base.wrapping_add(way as u32 * 4);
+            u32x4_add(u32x4(0, 1, 2, 3), u32x4_splat(base))
+        })
+    }
+
+    #[inline(always)]
+    unsafe fn hits(word0: Self, mask: Self) -> u64 {
+        let masked: [v128; WAYS] = core::array::from_fn(|way| v128_and(word0[way], mask[way]));
+        // a lane that is zero in any way is zero in the AND of all ways, so a single check rules
+        // out hits in every way, the rare false positive is sorted out below
+        let all = masked
+            .iter()
+            .fold(u32x4_splat(!0), |all, masked| v128_and(all, *masked));
+        if u32x4_all_true(all) {
+            return 0;
+        }
+
+        masked.iter().enumerate().fold(0, |hits, (way, masked)| {
+            let zero = u32x4_eq(*masked, u32x4_splat(0));
+            hits | u64::from(u32x4_bitmask(zero)) << (way * 4)
+        })
+    }
+
+    #[inline(always)]
+    unsafe fn store(self, lanes: &mut [u32; 64]) {
+        for (way, lanes) in lanes.chunks_exact_mut(4).take(WAYS).enumerate() {
+            // SAFETY: the chunk has room for the four lanes
+            unsafe { v128_store(lanes.as_mut_ptr().cast(), self[way]) };
+        }
+    }
+}
 
 /// SIMD128 Ceberus solver.
-pub struct CerberusSolver {
+///
+/// Keeps `WAYS` independent four-lane states in flight per iteration, so wide out-of-order cores
+/// can overlap the dependency chains of the round function.
+pub struct CerberusSolver<const WAYS: usize = 1> {
     message: CerberusMessage,
     report_slot: u32,
+    nonces: Range<u32>,
 }
 
-impl CerberusSolver {
-    const REPORT_PERIOD: u32 = 8192;
+impl<const WAYS: usize> CerberusSolver<WAYS> {
+    const REPORT_PERIOD: u32 = 32768 / <[v128; WAYS]>::LANES;
 }
 
-impl From<CerberusMessage> for CerberusSolver {
+impl<const WAYS: usize> From<CerberusMessage> for CerberusSolver<WAYS> {
     fn from(message: CerberusMessage) -> Self {
         Self {
             message,
             report_slot: 0,
+            nonces: 0..u32::MAX,
         }
     }
 }
 
-impl crate::solver::Solver for CerberusSolver {
+impl<const WAYS: usize> crate::solver::Solver for CerberusSolver<WAYS> {
     fn set_report_slot(&mut self, tid: u32, threads: u32) {
-        self.report_slot = tid * Self::REPORT_PERIOD / threads;
+        self.report_slot = super::report_slot(tid, threads, Self::REPORT_PERIOD);
     }
 
-    #[inline(never)]
-    fn solve<P: FnMut(u32)>(&mut self, mask: u32, mut progress: P) -> Option<([u32; 2], [u32; 8])> {
-        let mut msg = [0; 16];
-        msg[0] = self.message.batch_id;
-
-        let midstate = crate::blake3::setup_block(
-            self.message.midstate,
-            0,
-            8,
-            self.message.trailing_block_flags(),
-        );
-        let midstate = core::array::from_fn(|i| u32x4_splat(midstate[i] as _));
-
-        let mut nonce = u32x4(0, 1, 2, 3);
-        let four = u32x4_splat(4);
-        let maskv = u32x4_splat(mask);
-        for rep in 0..(u32::MAX / 4) {
-            let mut state = midstate;
-            crate::blake3::simd128::compress_mb4::<1>(&mut state, &msg, nonce);
-            let masked = v128_and(state[0], maskv);
-            nonce = u32x4_add(nonce, four);
-
-            if !u32x4_all_true(masked) {
-                crate::unlikely();
-
-                let mut extract = [0u32; 4];
-                unsafe { v128_store(extract.as_mut_ptr().cast(), masked) };
-                let success_lane_idx = extract.iter().position(|x| *x & mask == 0).unwrap();
-                msg[1] = rep * 4 + success_lane_idx as u32;
-
-                let hash = crate::blake3::compress8(
-                    &self.message.midstate,
-                    &msg,
-                    0,
-                    8,
-                    self.message.trailing_block_flags(),
-                );
-
-                return Some(([self.message.batch_id, msg[1]], hash));
-            }
+    fn set_nonce_range(&mut self, nonces: Range<u32>) {
+        self.nonces = nonces;
+    }
 
-            if rep % Self::REPORT_PERIOD == self.report_slot {
-                progress(Self::REPORT_PERIOD * 4);
-            }
+    #[inline(never)]
+    fn solve<P: FnMut(u32) -> ControlFlow<()>>(
+        &mut self,
+        target: &crate::Target,
+        progress: P,
+    ) -> Option<([u32; 2], [u32; 8])> {
+        // SAFETY: simd128 is enabled at compile time
+        unsafe {
+            solve_lanes::<[v128; WAYS], _>(
+                &self.message,
+                &self.nonces,
+                Self::REPORT_PERIOD,
+                self.report_slot,
+                target,
+                progress,
+            )
         }
-
-        None
     }
 }
 
@@ -83,7 +102,15 @@ mod tests {
     #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn test_solve_cerberus() {
         crate::solver::tests::test_cerberus_validator::<CerberusSolver, _>(|prefix| {
-            CerberusMessage::new(prefix, 0).map(Into::into)
+            CerberusMessage::new(prefix, 0).ok().map(Into::into)
+        });
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_solve_cerberus_interleaved() {
+        crate::solver::tests::test_cerberus_validator::<CerberusSolver<2>, _>(|prefix| {
+            CerberusMessage::new(prefix, 0).ok().map(Into::into)
         });
     }
 }
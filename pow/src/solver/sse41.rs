@@ -0,0 +1,107 @@
+use super::lanes::{solve_lanes, Lanes};
+use crate::CerberusMessage;
+use core::arch::x86_64::*;
+use core::ops::{ControlFlow, Range};
+
+impl Lanes for __m128i {
+    const LANES: u32 = 4;
+
+    #[inline(always)]
+    unsafe fn nonces(base: u32) -> Self {
+        unsafe { _mm_add_epi32(_mm_setr_epi32(0, 1, 2, 3), _mm_set1_epi32(base as _)) }
+    }
+
+    #[inline(always)]
+    unsafe fn hits(word0: Self, mask: Self) -> u64 {
+        unsafe {
+            let zero = _mm_cmpeq_epi32(_mm_and_si128(word0, mask), _mm_setzero_si128());
+            _mm_movemask_ps(_mm_castsi128_ps(zero)) as u64
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn store(self, lanes: &mut [u32; 64]) {
+        unsafe { _mm_storeu_si128(lanes.as_mut_ptr().cast(), self) }
+    }
+}
+
+/// SSE4.1 Ceberus solver.
+pub struct CerberusSolver {
+    message: CerberusMessage,
+    report_slot: u32,
+    nonces: Range<u32>,
+}
+
+impl CerberusSolver {
+    const REPORT_PERIOD: u32 = 8192;
+
+    /// Whether the CPU supports this solver
+    pub fn is_supported() -> bool {
+        std::is_x86_feature_detected!("sse4.1")
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn solve_impl<P: FnMut(u32) -> ControlFlow<()>>(
+        &mut self,
+        target: &crate::Target,
+        progress: P,
+    ) -> Option<([u32; 2], [u32; 8])> {
+        // SAFETY: SSE4.1 is enabled on this function
+        unsafe {
+            solve_lanes::<__m128i, _>(
+                &self.message,
+                &self.nonces,
+                Self::REPORT_PERIOD,
+                self.report_slot,
+                target,
+                progress,
+            )
+        }
+    }
+}
+
+impl From<CerberusMessage> for CerberusSolver {
+    fn from(message: CerberusMessage) -> Self {
+        Self {
+            message,
+            report_slot: 0,
+            nonces: 0..u32::MAX,
+        }
+    }
+}
+
+impl crate::solver::Solver for CerberusSolver {
+    fn set_report_slot(&mut self, tid: u32, threads: u32) {
+        self.report_slot = super::report_slot(tid, threads, Self::REPORT_PERIOD);
+    }
+
+    fn set_nonce_range(&mut self, nonces: Range<u32>) {
+        self.nonces = nonces;
+    }
+
+    #[inline(never)]
+    fn solve<P: FnMut(u32) -> ControlFlow<()>>(
+        &mut self,
+        target: &crate::Target,
+        progress: P,
+    ) -> Option<([u32; 2], [u32; 8])> {
+        assert!(Self::is_supported(), "SSE4.1 is not supported on this CPU");
+        // SAFETY: the required target feature was detected above
+        unsafe { self.solve_impl(target, progress) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_cerberus() {
+        if !CerberusSolver::is_supported() {
+            return;
+        }
+        crate::solver::tests::test_cerberus_validator::<CerberusSolver, _>(|prefix| {
+            CerberusMessage::new(prefix, 0).ok().map(Into::into)
+        });
+    }
+}
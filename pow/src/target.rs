@@ -0,0 +1,334 @@
+//! 256-bit difficulty targets
+//!
+//! Cerberus compares hashes as big endian integers (i.e. the order of the hex string the server
+//! sees), while BLAKE3 outputs little endian words, so every word is byte swapped before comparing.
+
+/// A difficulty target.
+///
+/// A hash meets the target when, read as a 256-bit big endian integer, it is not greater than
+/// the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    /// The threshold as big endian words, most significant first
+    threshold: [u32; 8],
+    /// Mask on the first (little endian) hash word that rejects most hashes above the threshold
+    prefilter: u32,
+}
+
+impl Target {
+    /// The target every hash meets
+    pub const MAX: Self = Self::from_words([!0; 8]);
+
+    const fn from_words(threshold: [u32; 8]) -> Self {
+        // Any bit set above the leading zeros of the first threshold word exceeds the threshold
+        let leading_zeros = threshold[0].leading_zeros();
+        let prefilter = if leading_zeros == 32 {
+            !0
+        } else {
+            !(!0u32 >> leading_zeros)
+        };
+        Self {
+            threshold,
+            prefilter: prefilter.swap_bytes(),
+        }
+    }
+
+    /// Create a target from a big endian threshold
+    pub const fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        let mut threshold = [0; 8];
+        let mut i = 0;
+        while i < 8 {
+            threshold[i] = u32::from_be_bytes([
+                bytes[i * 4],
+                bytes[i * 4 + 1],
+                bytes[i * 4 + 2],
+                bytes[i * 4 + 3],
+            ]);
+            i += 1;
+        }
+        Self::from_words(threshold)
+    }
+
+    /// The threshold as big endian bytes
+    pub const fn to_be_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        let mut i = 0;
+        while i < 32 {
+            bytes[i] = self.threshold[i / 4].to_be_bytes()[i % 4];
+            i += 1;
+        }
+        bytes
+    }
+
+    /// Create a target that requires `bits` leading zero bits.
+    ///
+    /// Returns None if `bits` is greater than 256.
+    pub const fn from_leading_zero_bits(bits: u16) -> Option<Self> {
+        if bits > 256 {
+            return None;
+        }
+        let mut threshold = [0; 8];
+        let mut i = 0;
+        while i < 8 {
+            let word_start = i as u16 * 32;
+            threshold[i] = if bits <= word_start {
+                !0
+            } else if bits >= word_start + 32 {
+                0
+            } else {
+                !0 >> (bits - word_start)
+            };
+            i += 1;
+        }
+        Some(Self::from_words(threshold))
+    }
+
+    /// Create a target that requires `dubits` leading zero dubits (pairs of bits), which is how
+    /// the server expresses difficulty.
+    ///
+    /// Returns None if `dubits` is greater than 128.
+    pub const fn from_dubits(dubits: u8) -> Option<Self> {
+        Self::from_leading_zero_bits(dubits as u16 * 2)
+    }
+
+    /// Create the target for a difficulty as received from the server or the page.
+    ///
+    /// This is [`Target::from_dubits`], except that a difficulty of 0 is rejected.
+    pub fn from_difficulty(difficulty: u32) -> Option<Self> {
+        let difficulty = core::num::NonZeroU8::new(difficulty.try_into().ok()?)?;
+        Self::from_dubits(difficulty.get())
+    }
+
+    /// Create the target the server checks answers against: `difficulty / 2` leading zero hex
+    /// digits, and one more zero bit for an odd difficulty.
+    ///
+    /// For an odd difficulty this is one bit less than [`Target::from_difficulty`], so solutions
+    /// meeting that target meet this one too. Returns None for the same difficulties.
+    pub fn from_answer_difficulty(difficulty: u32) -> Option<Self> {
+        Self::from_difficulty(difficulty)?;
+        Self::from_leading_zero_bits((difficulty * 2 - difficulty % 2) as u16)
+    }
+
+    /// Create a target that takes `attempts` hashes to meet on average.
+    ///
+    /// Unlike [`Target::from_dubits`] this allows any step in between powers of two.
+    pub const fn from_expected_attempts(attempts: u64) -> Self {
+        if attempts <= 1 {
+            return Self::MAX;
+        }
+        // floor(2^256 / attempts) - 1 by long division, one word at a time
+        let divisor = attempts as u128;
+        let mut remainder = 1u128;
+        let mut threshold = [0; 8];
+        let mut i = 0;
+        while i < 8 {
+            let current = remainder << 32;
+            threshold[i] = (current / divisor) as u32;
+            remainder = current % divisor;
+            i += 1;
+        }
+        let mut i = 8;
+        while i > 0 {
+            i -= 1;
+            let (word, borrow) = threshold[i].overflowing_sub(1);
+            threshold[i] = word;
+            if !borrow {
+                break;
+            }
+        }
+        Self::from_words(threshold)
+    }
+
+    /// Mask on the first hash word: a hash can only meet the target if `hash[0] & mask == 0`.
+    pub const fn prefilter_mask(&self) -> u32 {
+        self.prefilter
+    }
+
+    /// Whether the hash, in BLAKE3 output word order, meets the target
+    #[inline]
+    pub fn is_met(&self, hash: &[u32; 8]) -> bool {
+        for (word, threshold) in hash.iter().zip(self.threshold) {
+            let word = word.swap_bytes();
+            if word != threshold {
+                return word < threshold;
+            }
+        }
+        true
+    }
+
+    /// The expected number of attempts to find a hash meeting the target
+    pub fn expected_attempts(&self) -> f64 {
+        let mut probability = 0.0;
+        for (i, word) in self.threshold.iter().enumerate() {
+            probability += f64::from(*word) * 2f64.powi(-32 * (i as i32 + 1));
+        }
+        1.0 / (probability + 2f64.powi(-256))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash_from_be_bytes(bytes: [u8; 32]) -> [u32; 8] {
+        core::array::from_fn(|i| u32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap()))
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_prefilter_matches_legacy_mask() {
+        for df in 1..=16u8 {
+            let target = Target::from_dubits(df).unwrap();
+            let legacy = if df == 16 {
+                !0
+            } else {
+                !(!0u32 >> (df * 2)).swap_bytes()
+            };
+            assert_eq!(target.prefilter_mask(), legacy, "difficulty {df}");
+        }
+        for df in 17..=128u8 {
+            assert_eq!(Target::from_dubits(df).unwrap().prefilter_mask(), !0);
+        }
+        assert_eq!(Target::from_dubits(0).unwrap(), Target::MAX);
+        assert_eq!(Target::MAX.prefilter_mask(), 0);
+        assert_eq!(Target::from_dubits(129), None);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_is_met() {
+        let mut threshold = [0xffu8; 32];
+        threshold[..5].copy_from_slice(&[0, 0, 0, 0, 0x80]);
+        let target = Target::from_leading_zero_bits(33).unwrap();
+        assert_eq!(target.to_be_bytes(), {
+            threshold[4] = 0x7f;
+            threshold
+        });
+
+        let mut hash = [0u8; 32];
+        assert!(target.is_met(&hash_from_be_bytes(hash)));
+        hash[4] = 0x7f;
+        hash[31] = 0xff;
+        assert!(target.is_met(&hash_from_be_bytes(hash)));
+        hash[4] = 0x80;
+        assert!(!target.is_met(&hash_from_be_bytes(hash)));
+        hash[4] = 0;
+        hash[3] = 1;
+        assert!(!target.is_met(&hash_from_be_bytes(hash)));
+        assert_eq!(
+            hash_from_be_bytes(hash)[0] & target.prefilter_mask(),
+            1 << 24
+        );
+
+        // a threshold that is not a power of two can be met exactly
+        let target = Target::from_be_bytes(core::array::from_fn(|i| [0, 0x12, 0x34][i.min(2)]));
+        let mut hash = [0u8; 32];
+        hash[1] = 0x12;
+        hash[2..].fill(0x34);
+        assert!(target.is_met(&hash_from_be_bytes(hash)));
+        hash[31] = 0x35;
+        assert!(!target.is_met(&hash_from_be_bytes(hash)));
+        hash[30] = 0x33;
+        assert!(target.is_met(&hash_from_be_bytes(hash)));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_from_difficulty() {
+        for difficulty in [1, 8, 128] {
+            assert_eq!(
+                Target::from_difficulty(difficulty),
+                Target::from_dubits(difficulty as u8)
+            );
+        }
+        for difficulty in [0, 129, 256, u32::MAX] {
+            assert_eq!(Target::from_difficulty(difficulty), None);
+        }
+    }
+
+    /// `checkAnswer` of the server
+    fn check_answer(hex: &str, difficulty: usize) -> bool {
+        let (nibbles, remaining) = (difficulty / 2, difficulty % 2);
+        if !hex.starts_with(&"0".repeat(nibbles)) {
+            return false;
+        }
+        remaining == 0 || hex.as_bytes()[nibbles] < b'8'
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_from_answer_difficulty() {
+        for difficulty in 1..=24 {
+            let target = Target::from_answer_difficulty(difficulty).unwrap();
+            for zeros in 0..16 {
+                for nibble in 0..16u8 {
+                    let mut hash = *::blake3::hash(&[zeros, nibble]).as_bytes();
+                    hash[..zeros as usize / 2].fill(0);
+                    let last = &mut hash[zeros as usize / 2];
+                    *last = if zeros % 2 == 0 {
+                        nibble << 4 | *last & 0xf
+                    } else {
+                        nibble
+                    };
+
+                    let hex = ::blake3::Hash::from_bytes(hash).to_hex();
+                    assert_eq!(
+                        target.is_met(&hash_from_be_bytes(hash)),
+                        check_answer(&hex, difficulty as usize),
+                        "difficulty {difficulty}, hash {hex}"
+                    );
+                }
+            }
+        }
+        for difficulty in [0, 129, 256, u32::MAX] {
+            assert_eq!(Target::from_answer_difficulty(difficulty), None);
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_verify_matches_check_answer() {
+        // the inputs of TestVerifyAnswerMatchesGo in the directives package
+        for data in [
+            "",
+            "challenge|0|1700000000|signature|",
+            "Accept-Language=en,X-Real-IP=127.0.0.1",
+        ] {
+            for nonce in [0, 1, 0xffffffff, 1 << 32, 0x123456789abcdef0] {
+                for difficulty in [1, 4, 16] {
+                    let (hash, ok) = match crate::verify(data, difficulty, nonce) {
+                        Ok(hash) => (hash, true),
+                        Err(crate::VerifyError::InsufficientWork(hash)) => (hash, false),
+                        Err(error) => panic!("{error}"),
+                    };
+                    assert_eq!(
+                        ok,
+                        check_answer(&hash.to_hex(), difficulty as usize),
+                        "{data:?}, nonce {nonce}, difficulty {difficulty}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_expected_attempts() {
+        for bits in [0, 1, 12, 31, 32, 33, 100, 255] {
+            let target = Target::from_leading_zero_bits(bits).unwrap();
+            assert_eq!(target.expected_attempts(), 2f64.powi(bits as i32));
+            if bits < 64 {
+                assert_eq!(Target::from_expected_attempts(1 << bits), target);
+            }
+        }
+        assert_eq!(
+            Target::from_leading_zero_bits(256).unwrap().threshold,
+            [0; 8]
+        );
+
+        let target = Target::from_expected_attempts(3 << 20);
+        assert!((target.expected_attempts() / (3 << 20) as f64 - 1.0).abs() < 1e-9);
+        assert_eq!(target.threshold[0], 0x555);
+        assert_eq!(target.prefilter_mask().count_ones(), 21);
+    }
+}
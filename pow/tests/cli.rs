@@ -0,0 +1,85 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const DATA: &str = "Accept-Language=en,X-Real-IP=127.0.0.1";
+
+fn check_output(stdout: &[u8], difficulty: u32) {
+    let resp: serde_json::Value = serde_json::from_slice(stdout).unwrap();
+    assert_eq!(resp["difficulty"], difficulty);
+    let nonce = resp["nonce"].as_u64().unwrap();
+    let hash = pow::verify(DATA, difficulty, nonce).unwrap();
+    assert_eq!(resp["hash"], hash.to_hex());
+}
+
+#[test]
+fn test_solve_args() {
+    let output = Command::new(env!("CARGO_BIN_EXE_cerberus-solve"))
+        .args(["--threads", "2", DATA, "5"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    check_output(&output.stdout, 5);
+}
+
+#[test]
+fn test_solve_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cerberus-solve"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let challenge = serde_json::json!({ "data": DATA, "difficulty": 6 });
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(challenge.to_string().as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    check_output(&output.stdout, 6);
+}
+
+#[test]
+fn test_invalid_input() {
+    let output = Command::new(env!("CARGO_BIN_EXE_cerberus-solve"))
+        .args([DATA, "0"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cerberus-solve"))
+        .args([DATA])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_backends() {
+    let output = Command::new(env!("CARGO_BIN_EXE_cerberus-solve"))
+        .arg("--list-backends")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let listing = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(listing.lines().count(), pow::Backend::ALL.len());
+    assert!(listing.contains(&format!("{}\tdefault", pow::Backend::detect())));
+
+    for backend in pow::Backend::supported() {
+        let output = Command::new(env!("CARGO_BIN_EXE_cerberus-solve"))
+            .args(["--backend", backend.name(), DATA, "5"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "backend {backend}");
+        check_output(&output.stdout, 5);
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cerberus-solve"))
+        .args(["--backend", "nonexistent", DATA, "5"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,54 @@
+/* Smoke test of the C ABI, built against include/cerberus_pow.h and the staticlib:
+ *
+ *   cargo build --release
+ *   cc -Iinclude tests/ffi_smoke.c target/release/libpow.a -lm -ldl -lpthread -o target/ffi_smoke
+ *   target/ffi_smoke
+ */
+#include <stdio.h>
+#include <string.h>
+
+#include "cerberus_pow.h"
+
+#define CHECK(cond)                                                      \
+  do {                                                                   \
+    if (!(cond)) {                                                       \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+      return 1;                                                          \
+    }                                                                    \
+  } while (0)
+
+int main(void) {
+  const char *data = "Accept-Language=en,X-Real-IP=127.0.0.1";
+  size_t data_len = strlen(data);
+  uint64_t nonce = 0;
+  uint8_t hash[32];
+  uint8_t verified[32];
+
+  CHECK(cerberus_compute_mask(0) == 0);
+  CHECK(cerberus_compute_mask(4) == 0xff);
+
+  CHECK(cerberus_solve((const uint8_t *)data, data_len, 4, &nonce, hash) == CERBERUS_STATUS_OK);
+  CHECK(hash[0] == 0);
+  CHECK(cerberus_verify((const uint8_t *)data, data_len, 4, nonce, verified) == CERBERUS_STATUS_OK);
+  CHECK(memcmp(hash, verified, sizeof(hash)) == 0);
+
+  uint64_t attempts = 0;
+  CHECK(cerberus_solve_parallel((const uint8_t *)data, data_len, 5, 2, &nonce, NULL, &attempts) ==
+        CERBERUS_STATUS_OK);
+  CHECK(attempts > 0);
+  CHECK(cerberus_verify((const uint8_t *)data, data_len, 5, nonce, NULL) == CERBERUS_STATUS_OK);
+
+  uint8_t key[32];
+  memset(key, 0x42, sizeof(key));
+  CHECK(cerberus_solve_keyed(key, (const uint8_t *)data, data_len, 4, &nonce, hash) ==
+        CERBERUS_STATUS_OK);
+  CHECK(cerberus_verify_keyed(key, (const uint8_t *)data, data_len, 4, nonce, NULL) ==
+        CERBERUS_STATUS_OK);
+
+  CHECK(cerberus_verify((const uint8_t *)data, data_len, 0, nonce, NULL) ==
+        CERBERUS_STATUS_INVALID_DIFFICULTY);
+  CHECK(cerberus_solve(NULL, 4, 4, &nonce, NULL) == CERBERUS_STATUS_INVALID_ARGUMENT);
+
+  puts("ok");
+  return 0;
+}
@@ -112,36 +112,25 @@ const main = async () => {
   ui.progress(0);
 
   const t0 = Date.now();
-  let lastUpdate = 0;
 
   const likelihood = Math.pow(16, -difficulty / 2);
 
-  let totalIters = 0;
-
   const mergedChallenge = `${challenge}|${inputNonce}|${ts}|${signature}|`;
-  const { hash, nonce: solution } = await pow(mergedChallenge, difficulty, null, (iters) => {
-    // the probability of still being on the page is (1 - likelihood) ^ iters.
+  const { hash, nonce: solution } = await pow(mergedChallenge, difficulty, null, ({ attempts, hashrate }) => {
+    // the probability of still being on the page is (1 - likelihood) ^ attempts.
     // by definition, half of the time the progress bar only gets to half, so
     // apply a polynomial ease-out function to move faster in the beginning
     // and then slow down as things get increasingly unlikely. quadratic felt
     // the best in testing, but this may need adjustment in the future.
-    totalIters += iters;
-    const probability = Math.pow(1 - likelihood, totalIters);
+    const probability = Math.pow(1 - likelihood, attempts);
     const distance = (1 - Math.pow(probability, 2)) * 100;
 
-    // Update progress every 200ms
-    const now = Date.now();
-    const delta = now - t0;
-
-    if (delta - lastUpdate > 200) {
-      const speed = totalIters / delta;
-      ui.progress(distance);
-      ui.metrics(t('challenge.difficulty_speed', { difficulty, speed: speed.toFixed(3) }));
-      if (probability < 0.01) {
-        ui.progressMessage(t('challenge.taking_longer'));
-      }
-      lastUpdate = delta;
-    };
+    // workers report on a fixed interval, so every report updates the page
+    ui.progress(distance);
+    ui.metrics(t('challenge.difficulty_speed', { difficulty, speed: (hashrate / 1000).toFixed(3) }));
+    if (probability < 0.01) {
+      ui.progressMessage(t('challenge.taking_longer'));
+    }
   }, () => {
     ui.progressMessage(t('challenge.wasm_unavailable'));
   });
@@ -1,13 +1,24 @@
 // Pure JS PoW worker (fallback when WebAssembly is unavailable)
 import { compress8, blake3Hash, encodeHexLE, computeMask, IV, FLAG_CHUNK_START, FLAG_CHUNK_END, FLAG_ROOT } from './blake3.js';
+// the protocol constants of the pow crate
+import { version as PROTOCOL_VERSION, progressIntervalMs as PROGRESS_INTERVAL_MS } from 'pow-wasm-mvp/protocol.json';
 
 const REPORT_PERIOD = 16384;
 
 addEventListener('message', (event) => {
-  const { data, difficulty, nonce: threadId, threads } = event.data;
+  const {
+    task: { data, difficulty, threadId, threads },
+    // computed once by the page
+    expectedAttempts,
+    options: { cancel, progress, batches },
+  } = event.data;
+  postMessage({ type: "hello", version: PROTOCOL_VERSION, backend: "js" });
 
   const mask = computeMask(difficulty);
   const reportSlot = (threadId * REPORT_PERIOD / threads) | 0;
+  const started = Date.now();
+  let nextReport = started + PROGRESS_INTERVAL_MS;
+  let totalAttempts = 0;
 
   // Compute salt = blake3(data) as hex bytes
   const encoder = new TextEncoder();
@@ -32,7 +43,12 @@ addEventListener('message', (event) => {
   }
   const midstate = compress8(IV, initBlock, 0, 64, FLAG_CHUNK_START);
 
-  let set = threadId;
+  // claim batches from the shared cursor when there is one, otherwise take every threads-th
+  const nextBatch = (last) => {
+    if (batches) return Number(Atomics.add(batches, 0, 1n));
+    return last === undefined ? threadId : last + threads;
+  };
+  let set = nextBatch();
   const trailingFlags = FLAG_CHUNK_END | FLAG_ROOT;
 
   while (true) {
@@ -47,7 +63,20 @@ addEventListener('message', (event) => {
       attemptedNonces++;
 
       if (attemptedNonces % REPORT_PERIOD === reportSlot) {
-        postMessage(REPORT_PERIOD);
+        totalAttempts += REPORT_PERIOD;
+        const now = Date.now();
+        if (progress) {
+          Atomics.add(progress, threadId, BigInt(REPORT_PERIOD));
+        } else if (now >= nextReport) {
+          nextReport = now + PROGRESS_INTERVAL_MS;
+          const hashrate = totalAttempts / ((now - started) / 1000);
+          const eta = expectedAttempts / (hashrate * threads);
+          postMessage({ type: "progress", attempts: totalAttempts, hashrate, eta });
+        }
+        if (cancel && Atomics.load(cancel, 0) !== 0) {
+          postMessage({ type: "cancelled" });
+          return;
+        }
       }
 
       if ((hash[0] & mask) === 0) {
@@ -55,6 +84,7 @@ addEventListener('message', (event) => {
         // solution = nonce as u64 | (batchId as u64) << 32
         const solution = nonce + set * 0x100000000;
         postMessage({
+          type: "solved",
           hash: hashHex,
           difficulty,
           nonce: solution,
@@ -64,7 +94,7 @@ addEventListener('message', (event) => {
     }
 
     // Exhausted nonce space for this batch_id, try next
-    set += threads;
+    set = nextBatch(set);
     if (set > 0xFFFFFFFF) return;
   }
 });
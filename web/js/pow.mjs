@@ -1,5 +1,7 @@
 import PowWorker from './pow.worker.js?worker&inline';
 import PowJsWorker from './pow.js.worker.js?worker&inline';
+// the worker message protocol spoken by this page, see WorkerMessage in the pow crate
+import { version as PROTOCOL_VERSION, progressIntervalMs as PROGRESS_INTERVAL_MS } from "pow-wasm-mvp/protocol.json";
 import wasmUrlMvp from "pow-wasm-mvp/pow_mvp_bg.wasm?url";
 import wasmUrlSimd from "pow-wasm-simd/pow_simd_bg.wasm?url";
 
@@ -23,6 +25,25 @@ const supportsSimd = () => {
   }
 };
 
+// A task the solver rejected, with `code` telling the reasons apart for logging
+export class PowTaskError extends Error {
+  constructor({ code, message }) {
+    super(message);
+    this.name = "PowTaskError";
+    this.code = code;
+  }
+}
+
+// Workers can only be stopped without terminating them, count their attempts without posting
+// a message per report, and share batches of work through memory shared with the page, which
+// requires cross-origin isolation. The challenge page is served with COOP and COEP only when the
+// middleware sets cross_origin_isolated, otherwise workers post messages and are terminated.
+// Stopped workers are kept for the next challenge.
+const supportsSharedMemory = () =>
+  typeof SharedArrayBuffer !== "undefined" && globalThis.crossOriginIsolated === true;
+
+const idleWorkers = new Map();
+
 export default async function process(
   data,
   difficulty = 5,
@@ -32,22 +53,101 @@ export default async function process(
   threads = (navigator.hardwareConcurrency || 1),
 ) {
   const workers = [];
-  const runWorkers = (WorkerClass, message) => Promise.race(
-    Array(threads).fill(0).map((i, idx) => new Promise((resolve, reject) => {
-      const worker = new WorkerClass();
-      worker.onmessage = ({ data }) => (typeof data === "number" ? progressCallback : resolve)?.(data);
-      worker.onerror = reject;
-      worker.postMessage({
-        ...message,
-        data,
-        difficulty,
-        nonce: idx,
-        threads,
+  // the average attempts a solution takes: the solvers look for 2 * difficulty leading zero bits,
+  // the odds main.mjs shows progress with
+  const expectedAttempts = 4 ** difficulty;
+  const runWorkers = (WorkerClass, message) => {
+    const shared = supportsSharedMemory();
+    const cancel = shared ? new Int32Array(new SharedArrayBuffer(4)) : null;
+    // the attempts of every worker, polled instead of reported
+    const progress = shared ? new BigInt64Array(new SharedArrayBuffer(8 * threads)) : null;
+    // the next batch ID to search, claimed by whichever worker needs one first
+    const batches = shared ? new BigInt64Array(new SharedArrayBuffer(8)) : null;
+    // the latest progress of every worker, each counting its own attempts
+    const reports = [];
+    // every worker estimates from its own rate, they are all about as fast
+    let eta;
+    const reportProgress = () => {
+      const sum = (key) => reports.reduce((total, report) => total + report[key], 0);
+      progressCallback?.({ attempts: sum("attempts"), hashrate: sum("hashrate"), eta });
+    };
+    let poll;
+    if (progress) {
+      const started = Date.now();
+      poll = setInterval(() => {
+        const attempts = Number(progress.reduce((total, count) => total + count, 0n));
+        const hashrate = attempts / ((Date.now() - started) / 1000);
+        const eta = hashrate > 0 ? expectedAttempts / hashrate : undefined;
+        progressCallback?.({ attempts, hashrate, eta });
+      }, PROGRESS_INTERVAL_MS);
+    }
+    return Promise.race(
+      Array(threads).fill(0).map((i, idx) => new Promise((resolve, reject) => {
+        const worker = idleWorkers.get(WorkerClass)?.pop() ?? new WorkerClass();
+        // resolves to whether the worker can be reused once it has stopped
+        const stopped = new Promise((stop) => {
+          worker.onmessage = ({ data }) => {
+            switch (data.type) {
+              case "hello":
+                if (data.version !== PROTOCOL_VERSION) {
+                  stop(false);
+                  reject(new Error(`Unsupported worker protocol version ${data.version}`));
+                }
+                return;
+              case "progress":
+                if (cancel?.[0] === 1) return;
+                reports[idx] = data;
+                eta = data.eta ?? eta;
+                reportProgress();
+                return;
+              case "solved":
+                stop(true);
+                resolve(data);
+                return;
+              case "error":
+                stop(true);
+                reject(new PowTaskError(data));
+                return;
+              case "cancelled":
+                stop(true);
+                return;
+            }
+          };
+          worker.onerror = (error) => {
+            stop(false);
+            reject(error);
+          };
+        });
+        worker.postMessage({
+          ...message,
+          task: { type: "task", data, difficulty, threadId: idx, threads },
+          expectedAttempts,
+          options: { cancel, progress, batches },
+        });
+        signal?.addEventListener("abort", () => reject(new Error("PoW aborted")), { once: true });
+        workers.push({ WorkerClass, worker, cancel, stopped });
+      }))
+    ).finally(() => clearInterval(poll));
+  };
+
+  const releaseWorkers = () => {
+    for (const { WorkerClass, worker, cancel, stopped } of workers.splice(0)) {
+      if (!cancel) {
+        worker.terminate();
+        continue;
+      }
+      Atomics.store(cancel, 0, 1);
+      stopped.then((reusable) => {
+        if (!reusable) {
+          worker.terminate();
+          return;
+        }
+        worker.onmessage = worker.onerror = null;
+        if (!idleWorkers.has(WorkerClass)) idleWorkers.set(WorkerClass, []);
+        idleWorkers.get(WorkerClass).push(worker);
       });
-      signal?.addEventListener("abort", () => reject(new Error("PoW aborted")), { once: true });
-      workers.push(worker);
-    }))
-  );
+    }
+  };
 
   try {
     const useWasm = supportsWasm();
@@ -59,8 +159,9 @@ export default async function process(
         const wasmModule = await (await fetch(wasmUrl)).arrayBuffer();
         return await runWorkers(PowWorker, { wasmModule });
       } catch (error) {
-        workers.forEach((w) => w.terminate());
-        workers.length = 0;
+        // the fallback would fail the same way on bad input
+        if (error instanceof PowTaskError) throw error;
+        releaseWorkers();
         fallbackCallback?.(error);
       }
     } else {
@@ -69,6 +170,6 @@ export default async function process(
 
     return await runWorkers(PowJsWorker, {});
   } finally {
-    workers.forEach((w) => w.terminate());
+    releaseWorkers();
   }
 }
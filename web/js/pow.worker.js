@@ -7,5 +7,6 @@ addEventListener('message', (event) => {
     } catch (e) {
         throw new Error("Failed to initialize WebAssembly module", { cause: e });
     }
-    process_task(event.data.data, event.data.difficulty, event.data.nonce, event.data.threads);
+    const { task, options } = event.data;
+    process_task(task, options);
 });
@@ -50,18 +50,26 @@ test.describe(() => {
 
   // NOTE This test runs slowly in Firefox due to Playwright's devtools integration causing WebAssembly performance degradation
   // NOTE See: https://github.com/microsoft/playwright/issues/11102
-  test('must perform browser checks', async ({ page }) => {
-    const answerResponse = waitForAnswer(page, 303);
-
-    const response = await page.goto('/foo.iso', { waitUntil: 'commit' });
-
-    expect(response?.headers()['x-cerberus-status']).toBe('CHALLENGE');
-    await expect(page.getByText('Performing browser checks...')).toBeVisible();
-    await expect(page.getByText('Difficulty:')).toHaveText(/Difficulty: \d+, Speed: \d+(\.\d+)?kH\/s/);
-
-    await answerResponse;
-    await expect(page.getByText('Hello, foo.iso!')).toBeVisible();
-  });
+  for (const { path, isolated } of [
+    { path: '/foo.iso', isolated: false },
+    // served by the middleware with cross_origin_isolated, see tests/Caddyfile
+    { path: '/isolated/foo.iso', isolated: true },
+  ]) {
+    test(`must perform browser checks on ${path}`, async ({ page }) => {
+      const answerResponse = waitForAnswer(page, 303);
+
+      const response = await page.goto(path, { waitUntil: 'commit' });
+
+      expect(response?.headers()['x-cerberus-status']).toBe('CHALLENGE');
+      // the workers share memory with the page only when it is cross-origin isolated
+      expect(await page.evaluate(() => globalThis.crossOriginIsolated)).toBe(isolated);
+      await expect(page.getByText('Performing browser checks...')).toBeVisible();
+      await expect(page.getByText('Difficulty:')).toHaveText(/Difficulty: \d+, Speed: \d+(\.\d+)?kH\/s/);
+
+      await answerResponse;
+      await expect(page.getByText('Hello, foo.iso!')).toBeVisible();
+    });
+  }
 
   test('must fail when response is incorrect', async ({ page }) => {
     await page.route('**/.cerberus/answer', async (route, request) => {